@@ -0,0 +1,224 @@
+//! Defines an array of consecutive cells.
+
+use super::{cell::Cell, types::CellValue};
+use std::{ops, slice};
+
+/// Moves the head one cell to the right while its right counter is nonzero, decrementing it and
+/// carrying the value and left counter along. Starts and ends on the right counter.
+const WALK_RIGHT: &str = "[->[->+<]<[->+<]<[->+<]>>>>[-<<<<+>>>>]<<]";
+
+/// Moves the head one cell to the left while its left counter is nonzero, decrementing it and
+/// carrying the value along. Starts and ends on the left counter.
+const WALK_LEFT: &str = "[-[-<+>]<<<[->>>>+<<<<]>[-<+>]>]";
+
+/// Copies the element after the head into the value cell, starting on the right counter and ending
+/// on the left counter.
+const COPY_OUT: &str = ">>>[-<<<<+>>>+>]<[->+<]<";
+
+/// Moves the element after the head into the value cell, starting on the right counter and ending
+/// on the left counter.
+const MOVE_OUT: &str = ">>>[-<<<<+>>>>]<<";
+
+/// Replaces the element after the head with the value cell, starting on the right counter and
+/// ending on the left counter.
+const MOVE_IN: &str = ">>>[-]<<<<[->>>>+<<<<]>>";
+
+/// An array of `U` cells which are guaranteed to be consecutive in memory. Cells can be accessed by
+/// indices known while building the program, or by the value of another cell at runtime.
+///
+/// To support runtime indexing, the array is directly preceded by four scratch cells which make up
+/// a "head": a value, a counter for walking right, a counter for walking left, and a gap. The head
+/// walks over the array by moving each element it passes to its other side, so every scratch cell
+/// is zero whenever the head is at rest. `Builder::cell_array` allocates the head and elements as
+/// one consecutive block, which is the only way to create a `CellArray`.
+#[derive(Debug)]
+pub struct CellArray<'a, const N: usize, const U: usize, T: CellValue> {
+    pub(super) head: [Cell<'a, N, T>; 4],
+    pub(super) cells: [Cell<'a, N, T>; U],
+}
+
+impl<'a, const N: usize, const U: usize, T: CellValue> CellArray<'a, N, U, T> {
+    /// Gets the number of cells in this array.
+    pub fn len(&self) -> usize {
+        U
+    }
+
+    /// Checks whether this array has no cells.
+    pub fn is_empty(&self) -> bool {
+        U == 0
+    }
+
+    /// Gets the cell at `index`, or `None` if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> Option<&Cell<'a, N, T>> {
+        self.cells.get(index)
+    }
+
+    /// Gets mutable access to the cell at `index`, or `None` if `index` is out of bounds.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Cell<'a, N, T>> {
+        self.cells.get_mut(index)
+    }
+
+    /// Iterates over the cells in this array, starting with the first.
+    pub fn iter(&self) -> slice::Iter<'_, Cell<'a, N, T>> {
+        self.cells.iter()
+    }
+
+    /// Iterates mutably over the cells in this array, starting with the first.
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, Cell<'a, N, T>> {
+        self.cells.iter_mut()
+    }
+
+    /// Creates a new cell containing a copy of the element at the index stored in `index`. Both
+    /// `index` and the array are preserved.
+    ///
+    /// If `index` is not less than `U`, the generated program walks off the end of the array and
+    /// corrupts unrelated cells.
+    pub fn get_dynamic(&mut self, index: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        self.walk(index, COPY_OUT);
+        self.head[0].move_and_zero()
+    }
+
+    /// Sets the element at the index stored in `index` to a copy of `value`. Both `index` and
+    /// `value` are preserved.
+    ///
+    /// If `index` is not less than `U`, the generated program walks off the end of the array and
+    /// corrupts unrelated cells.
+    pub fn set_dynamic(&mut self, index: &Cell<'a, N, T>, value: &Cell<'a, N, T>) {
+        self.head[0] += value;
+        self.walk(index, MOVE_IN);
+    }
+
+    /// Creates a new cell containing the element at the index stored in `index`, and sets that
+    /// element to zero. `index` is preserved.
+    pub(super) fn take_dynamic(&mut self, index: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        self.walk(index, MOVE_OUT);
+        self.head[0].move_and_zero()
+    }
+
+    /// Walks the head right by `index` elements, runs `action` on the element after it, and walks
+    /// back.
+    fn walk(&mut self, index: &Cell<'a, N, T>, action: &str) {
+        self.head[1] += index;
+        self.head[2] += index;
+
+        let builder = self.head[1].builder;
+        self.head[1].goto();
+        *builder.source.borrow_mut() += WALK_RIGHT;
+        *builder.source.borrow_mut() += action;
+        *builder.source.borrow_mut() += WALK_LEFT;
+        builder.resync_pointer(&self.head[2]);
+    }
+
+    /// Consumes this array and returns the cells inside it.
+    pub fn into_cells(self) -> [Cell<'a, N, T>; U] {
+        self.cells
+    }
+}
+
+impl<'a, const N: usize, const U: usize, T: CellValue> ops::Index<usize>
+    for CellArray<'a, N, U, T>
+{
+    type Output = Cell<'a, N, T>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.cells[index]
+    }
+}
+
+impl<'a, const N: usize, const U: usize, T: CellValue> ops::IndexMut<usize>
+    for CellArray<'a, N, U, T>
+{
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.cells[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::core::Builder;
+    use std::num::Wrapping;
+
+    #[test]
+    fn static_indices_read_and_write_consecutive_cells() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut array = builder.cell_array([1, 2, 3, 4].map(Wrapping));
+        assert_eq!(array.len(), 4);
+        assert!(!array.is_empty());
+        assert!(array.get(4).is_none());
+
+        *array.get_mut(1).unwrap() += Wrapping(10);
+        array[3].zero();
+        array[0].inc();
+
+        for (index, cell) in array.iter().enumerate() {
+            assert_eq!(cell.location(), array[0].location() + index);
+            cell.write();
+        }
+
+        let runner = builder.run(std::iter::empty(), Vec::new()).unwrap();
+        assert_eq!(runner.output(), &[2, 12, 3, 0].map(Wrapping));
+        assert_eq!(runner.cell_value(array[1].location()), Wrapping(12));
+    }
+
+    #[test]
+    fn dynamic_indices_access_the_element_chosen_at_runtime() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut array = builder.cell_array([10, 20, 30, 40, 50].map(Wrapping));
+
+        // The index is only known once the program reads it, and is then doubled.
+        let mut index = builder.read();
+        index += &index.clone();
+        array.get_dynamic(&index).write();
+
+        let value = builder.read();
+        array.set_dynamic(&index, &value);
+        index.write();
+        value.write();
+        array.iter().for_each(|cell| cell.write());
+
+        let program = builder.compile().unwrap();
+
+        for (input, expected) in [
+            (0, [10, 0, 99, 99, 20, 30, 40, 50]),
+            (1, [30, 2, 99, 10, 20, 99, 40, 50]),
+            (2, [50, 4, 99, 10, 20, 30, 40, 99]),
+        ] {
+            let input = [Wrapping(input), Wrapping(99)].into_iter();
+            let runner = program.run::<32, _, _, _>(input, Vec::new());
+            assert_eq!(runner.output(), &expected.map(Wrapping));
+        }
+    }
+
+    #[test]
+    fn set_then_get_at_a_computed_index() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut array = builder.cell_array([Wrapping(0); 4]);
+
+        // Writes `value` to index `a * b - 1`, then reads every element back through the same
+        // kind of computed index.
+        let a = builder.read();
+        let b = builder.read();
+        let value = builder.read();
+        let mut index = a.clone();
+        index *= &b;
+        index.dec();
+        array.set_dynamic(&index, &value);
+
+        for position in 0..4 {
+            let index = builder.cell(Wrapping(position));
+            array.get_dynamic(&index).write();
+        }
+
+        let program = builder.compile().unwrap();
+
+        for (a, b, expected) in [
+            (1, 1, [7, 0, 0, 0]),
+            (2, 1, [0, 7, 0, 0]),
+            (2, 2, [0, 0, 0, 7]),
+        ] {
+            let input = [a, b, 7].map(Wrapping).into_iter();
+            let runner = program.run::<32, _, _, _>(input, Vec::new());
+            assert_eq!(runner.output(), &expected.map(Wrapping));
+        }
+    }
+}
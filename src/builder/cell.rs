@@ -1,7 +1,11 @@
 //! Adds functionality to `Cell` that does not depend on implementation details.
 
-use super::{core::Builder, types::CellValue};
-use std::{fmt, ops};
+use super::{
+    core::Builder,
+    op_log::OpKind,
+    types::{CellValue, SignedCellValue},
+};
+use std::{fmt, num::Wrapping, ops};
 
 /// An allocated cell.
 #[must_use]
@@ -10,28 +14,59 @@ pub struct Cell<'a, const N: usize, T: CellValue> {
     pub(super) location: usize,
 }
 
+/// Gets the pairs of indices compared and swapped by an optimal sorting network for `len` values,
+/// in the order they're applied.
+///
+/// # Panics
+///
+/// Panics if `len` is greater than 4.
+fn sorting_network(len: usize) -> &'static [(usize, usize)] {
+    match len {
+        0 | 1 => &[],
+        2 => &[(0, 1)],
+        3 => &[(1, 2), (0, 2), (0, 1)],
+        4 => &[(0, 1), (2, 3), (0, 2), (1, 3), (1, 2)],
+        _ => panic!("sort_small only supports up to 4 cells"),
+    }
+}
+
 impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
     /// Gets the underlying allocator this cell was created with.
     pub fn builder(&self) -> &'a Builder<N, T> {
         self.builder
     }
 
+    /// Gets the index of this cell in the memory tape, which can be passed to `Runner::cell_value`
+    /// to find the value of this cell after running the program.
+    pub fn location(&self) -> usize {
+        self.location
+    }
+
     /// Goes to this cell in memory.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the builder's pointer position is unknown, which happens after scanning for a zero
+    /// cell. Use `Builder::resync_pointer` to make it known again.
     pub fn goto(&self) {
         let mut source = self.builder.source.borrow_mut();
         let mut pointer = self.builder.pointer.borrow_mut();
 
-        if self.location < *pointer {
-            for _ in 0..*pointer - self.location {
+        let Some(current) = *pointer else {
+            panic!("cannot move to a cell when the pointer position is unknown");
+        };
+
+        if self.location < current {
+            for _ in 0..current - self.location {
                 source.push('<');
             }
-        } else if self.location > *pointer {
-            for _ in 0..self.location - *pointer {
+        } else if self.location > current {
+            for _ in 0..self.location - current {
                 source.push('>');
             }
         }
 
-        *pointer = self.location;
+        *pointer = Some(self.location);
     }
 
     /// Increments this cell.
@@ -60,6 +95,8 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
 
     /// Runs code while the value of this cell is nonzero.
     pub fn while_nonzero(&self, f: impl FnOnce()) {
+        let _op = self.builder.log_op(OpKind::Loop);
+
         {
             self.goto();
             *self.builder.source.borrow_mut() += "[";
@@ -76,6 +113,8 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
     /// Runs code while the value of this cell is nonzero, and provides mutable access to this cell
     /// in the process.
     pub fn while_nonzero_mut(&mut self, f: impl FnOnce(&mut Self)) {
+        let _op = self.builder.log_op(OpKind::Loop);
+
         {
             self.goto();
             *self.builder.source.borrow_mut() += "[";
@@ -89,6 +128,294 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
         }
     }
 
+    /// Runs code once if the value of this cell is nonzero. The value of this cell is preserved.
+    pub fn if_nonzero(&self, f: impl FnOnce()) {
+        let [mut flag] = self.copy();
+
+        flag.while_nonzero_mut(|flag| {
+            f();
+            flag.zero();
+        });
+    }
+
+    /// Runs code once if the value of this cell is nonzero, and provides mutable access to this
+    /// cell in the process. The condition is checked before `f` runs, so `f` may freely modify this
+    /// cell.
+    pub fn if_nonzero_mut(&mut self, f: impl FnOnce(&mut Self)) {
+        let [mut flag] = self.copy();
+
+        flag.while_nonzero_mut(|flag| {
+            f(self);
+            flag.zero();
+        });
+    }
+
+    /// Runs code once if the value of this cell is zero. The value of this cell is preserved.
+    pub fn if_zero(&self, f: impl FnOnce()) {
+        self.if_else(|| {}, f);
+    }
+
+    /// Runs `then` once if the value of this cell is nonzero, and `els` once otherwise. The value of
+    /// this cell is preserved.
+    pub fn if_else(&self, then: impl FnOnce(), els: impl FnOnce()) {
+        let mut is_zero = self.builder.cell(T::ZERO);
+        is_zero.inc();
+
+        self.if_nonzero(|| {
+            then();
+            is_zero.zero();
+        });
+
+        is_zero.while_nonzero_mut(|is_zero| {
+            els();
+            is_zero.zero();
+        });
+    }
+
+    /// Runs code once if the value of this cell is nonzero, zeroing this cell in the process. This
+    /// generates shorter code than `.if_nonzero()`, since this cell doesn't need to be copied.
+    pub fn if_nonzero_and_zero(&mut self, f: impl FnOnce()) {
+        self.while_nonzero_mut(|this| {
+            f();
+            this.zero();
+        });
+    }
+
+    /// Runs `then` once if the value of this cell is nonzero, and `els` once otherwise, zeroing
+    /// this cell in the process. This generates shorter code than `.if_else()`, since this cell
+    /// doesn't need to be copied.
+    pub fn if_else_and_zero(&mut self, then: impl FnOnce(), els: impl FnOnce()) {
+        let mut is_zero = self.builder.cell(T::ZERO);
+        is_zero.inc();
+
+        self.if_nonzero_and_zero(|| {
+            then();
+            is_zero.zero();
+        });
+
+        is_zero.if_nonzero_and_zero(els);
+    }
+
+    /// Runs code while the value of this cell is nonzero, at most as many times as the value of
+    /// `cap`, decrementing this cell after every iteration. This is effectively a loop which runs
+    /// `min(self, cap)` times. Afterwards, this cell holds however many iterations were cut off by
+    /// `cap`, and `cap` is preserved.
+    pub fn countdown_capped(&mut self, cap: &Cell<'a, N, T>, f: impl FnOnce()) {
+        let [mut remaining] = cap.copy();
+        let mut stash = self.builder.cell(T::ZERO);
+
+        self.while_nonzero_mut(|this| {
+            let mut cap_reached = remaining.not();
+            let mut cap_not_reached = remaining.normalized();
+
+            cap_reached.if_nonzero_and_zero(|| this.add_into_all_and_zero([&mut stash]));
+
+            cap_not_reached.if_nonzero_and_zero(|| {
+                f();
+                this.dec();
+                remaining.dec();
+            });
+        });
+
+        stash.add_into_all_and_zero([self]);
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is zero and 0 otherwise, which is
+    /// the logical negation of this cell. The value of this cell is preserved.
+    pub fn not(&self) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        result.inc();
+        self.if_nonzero(|| result.dec());
+        result
+    }
+
+    /// Sets this cell to 1 if its value is nonzero, and leaves it at 0 otherwise.
+    pub fn to_bool(&mut self) {
+        let mut value = self.move_and_zero();
+        value.if_nonzero_and_zero(|| self.inc());
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is nonzero and 0 otherwise. The
+    /// value of this cell is preserved.
+    fn normalized(&self) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        self.if_nonzero(|| result.inc());
+        result
+    }
+
+    /// Creates a new cell containing 1 if the values of this cell and `other` are both nonzero and
+    /// 0 otherwise. Both cells are preserved.
+    pub fn and(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut result = self.normalized();
+        result *= &other.normalized();
+        result
+    }
+
+    /// Creates a new cell containing 1 if either of the values of this cell and `other` are nonzero
+    /// and 0 otherwise. Both cells are preserved.
+    pub fn or(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut sum = self.normalized();
+        sum += &other.normalized();
+        sum.normalized()
+    }
+
+    /// Creates a new cell containing 1 if the values of this cell and `other` are equal and 0
+    /// otherwise. Both cells are preserved.
+    pub fn eq(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let [mut difference] = self.copy();
+        difference -= other;
+        difference.not()
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell equals `value` and 0 otherwise. This
+    /// is cheaper than `.eq()`, since no cell needs to be allocated for `value`. The value of this
+    /// cell is preserved.
+    pub fn eq_const(&self, value: T) -> Cell<'a, N, T> {
+        let [mut difference] = self.copy();
+        difference -= value;
+        difference.not()
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is less than the value of `other`
+    /// and 0 otherwise. Values are compared as unsigned numbers. Both cells are preserved.
+    pub fn lt(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        let [mut lhs] = self.copy();
+        let [mut rhs] = other.copy();
+
+        // Decrement both values until one of them hits zero. Neither value is decremented past
+        // zero, so this also works for types which don't wrap.
+        rhs.while_nonzero_mut(|rhs| {
+            let mut lhs_is_zero = lhs.not();
+            let mut lhs_is_nonzero = self.builder.cell(T::ZERO);
+            lhs_is_nonzero.inc();
+
+            lhs_is_zero.while_nonzero_mut(|lhs_is_zero| {
+                result.inc();
+                rhs.zero();
+                lhs_is_nonzero.zero();
+                lhs_is_zero.zero();
+            });
+
+            lhs_is_nonzero.while_nonzero_mut(|lhs_is_nonzero| {
+                lhs.dec();
+                rhs.dec();
+                lhs_is_nonzero.zero();
+            });
+        });
+
+        result
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is greater than the value of
+    /// `other` and 0 otherwise. Values are compared as unsigned numbers. Both cells are preserved.
+    pub fn gt(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        other.lt(self)
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is less than or equal to the value
+    /// of `other` and 0 otherwise. Values are compared as unsigned numbers. Both cells are
+    /// preserved.
+    pub fn le(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        self.gt(other).not()
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is greater than or equal to the
+    /// value of `other` and 0 otherwise. Values are compared as unsigned numbers. Both cells are
+    /// preserved.
+    pub fn ge(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        self.lt(other).not()
+    }
+
+    /// Creates a new cell containing 0 if the value of this cell is less than the value of `other`,
+    /// 1 if they are equal, and 2 if it is greater, which is convenient for sorting by a key. Values
+    /// are compared as unsigned numbers. Both cells are preserved.
+    pub fn compare(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut result = self.ge(other);
+        result += &self.gt(other);
+        result
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is between `low` and `high`
+    /// inclusive and 0 otherwise. Values are compared as unsigned numbers. This cell is preserved.
+    pub fn in_range(&self, low: T, high: T) -> Cell<'a, N, T> {
+        let low = self.builder.cell(low);
+        let high = self.builder.cell(high);
+
+        self.ge(&low).and(&self.le(&high))
+    }
+
+    /// Creates a new cell containing the value of this cell raised to the power of `exponent`, using
+    /// repeated multiplication. Both cells are preserved.
+    pub fn pow(&self, exponent: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        result.inc();
+
+        let [mut countdown] = exponent.copy();
+
+        countdown.while_nonzero_mut(|countdown| {
+            result *= self;
+            countdown.dec();
+        });
+
+        result
+    }
+
+    /// Creates a new cell containing the product of this cell and `other`, modulo `modulus`. The
+    /// product is reduced after every increment, so it is correct even when `self * other` would
+    /// not fit in a cell. `modulus` must be nonzero. All three cells are preserved.
+    pub fn mul_mod(&self, other: &Cell<'a, N, T>, modulus: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        let [mut countdown] = modulus.copy();
+
+        let step = self % modulus;
+        let mut times = other % modulus;
+
+        times.while_nonzero_mut(|times| {
+            let [mut step] = step.copy();
+
+            step.while_nonzero_mut(|step| {
+                step.dec();
+                result.inc();
+                countdown.dec();
+
+                let mut wrapped = countdown.not();
+
+                wrapped.while_nonzero_mut(|wrapped| {
+                    result.zero();
+                    countdown += modulus;
+                    wrapped.zero();
+                });
+            });
+
+            times.dec();
+        });
+
+        result
+    }
+
+    /// Creates a new cell containing the smaller of the values of this cell and `other`. Values are
+    /// compared as unsigned numbers. Both cells are preserved.
+    pub fn min(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let [mut result] = self.copy();
+        let mut other_is_smaller = other.lt(self);
+
+        other_is_smaller.if_nonzero_and_zero(|| result.clone_from(other));
+
+        result
+    }
+
+    /// Creates a new cell containing the larger of the values of this cell and `other`. Values are
+    /// compared as unsigned numbers. Both cells are preserved.
+    pub fn max(&self, other: &Cell<'a, N, T>) -> Cell<'a, N, T> {
+        let [mut result] = self.copy();
+        let mut other_is_larger = self.lt(other);
+
+        other_is_larger.if_nonzero_and_zero(|| result.clone_from(other));
+
+        result
+    }
+
     /// Sets the value of this cell to zero.
     pub fn zero(&mut self) {
         self.goto();
@@ -97,10 +424,171 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
 
     /// Sets the value of this cell to a given value.
     pub fn set(&mut self, value: T) {
+        let _op = self.builder.log_op(OpKind::Set);
+
         self.zero();
         *self += value;
     }
 
+    /// Negates the value of this cell. This is only meaningful for types which wrap, such as
+    /// `Wrapping<i8>`.
+    pub fn negate(&mut self) {
+        let mut temp = self.move_and_zero();
+
+        temp.while_nonzero_mut(|temp| {
+            temp.dec();
+            self.dec();
+        });
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is odd and 0 if it is even. This is
+    /// much cheaper than dividing by two. The value of this cell is preserved.
+    pub fn parity(&self) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        let [mut count] = self.copy();
+
+        count.while_nonzero_mut(|count| {
+            count.dec();
+
+            let mut flipped = self.builder.cell(T::ZERO);
+            flipped.inc();
+
+            result.while_nonzero_mut(|result| {
+                result.dec();
+                flipped.dec();
+            });
+
+            flipped.add_into_all_and_zero([&mut result]);
+        });
+
+        result
+    }
+
+    /// Maps the value of this cell through a table of `(from, to)` pairs. If the value of this cell
+    /// equals some `from`, it is replaced with the corresponding `to`. Only the first matching pair
+    /// is applied, and values not in the table are left unchanged.
+    pub fn map_through(&mut self, mapping: &[(T, T)]) {
+        let mut done = self.builder.cell(T::ZERO);
+
+        for &(from, to) in mapping {
+            let [mut difference] = self.copy();
+            difference -= from;
+
+            let mut matches = self.builder.cell(T::ZERO);
+            matches.inc();
+            difference.if_nonzero(|| matches.zero());
+            done.if_nonzero(|| matches.zero());
+
+            matches.while_nonzero_mut(|matches| {
+                self.set(to);
+                done.inc();
+                matches.zero();
+            });
+        }
+    }
+
+    /// Adds a constant to this cell. Small constants are added with a run of `+` or `-`, but larger
+    /// ones are split into `a * b + c` and added using a loop which runs `a` times, if that
+    /// generates shorter code.
+    pub(super) fn add_constant(&mut self, amount: isize) {
+        /// The largest amount which is always added with a run of `+` or `-`.
+        const MAX_UNARY_AMOUNT: usize = 16;
+
+        /// The approximate number of characters needed to allocate, loop over, and free a counter
+        /// cell, not including those needed to set its value or run the loop's body.
+        const LOOP_OVERHEAD: usize = 12;
+
+        let char = if amount < 0 { '-' } else { '+' };
+        let size = amount.unsigned_abs();
+
+        // Factors are chosen so that the remainder is never negative, which ensures the value of
+        // this cell never goes past its final value, even for types which don't wrap.
+        let factors = (2..=size.isqrt())
+            .map(|counter| (counter, size / counter, size % counter))
+            .min_by_key(|(counter, step, remainder)| counter + step + remainder);
+
+        // A counter which doesn't wrap must be able to hold its starting value exactly, which rules
+        // out factoring for types like `bool`.
+        let Some((counter, step, remainder)) = factors.filter(|(counter, step, remainder)| {
+            size > MAX_UNARY_AMOUNT
+                && counter + step + remainder + LOOP_OVERHEAD < size
+                && (T::WRAPS
+                    || T::ZERO.offset(*counter as isize).try_into_isize()
+                        == Some(*counter as isize))
+        }) else {
+            self.goto();
+            self.builder
+                .source
+                .borrow_mut()
+                .extend(std::iter::repeat_n(char, size));
+            return;
+        };
+
+        let mut temp = self.builder.cell(T::ZERO);
+        temp.add_constant(counter as isize);
+
+        temp.while_nonzero_mut(|temp| {
+            temp.dec();
+            self.goto();
+            self.builder
+                .source
+                .borrow_mut()
+                .extend(std::iter::repeat_n(char, step));
+        });
+
+        self.goto();
+        self.builder
+            .source
+            .borrow_mut()
+            .extend(std::iter::repeat_n(char, remainder));
+    }
+
+    /// Adds (or subtracts, if `negate` is true) a constant to this cell. Constants larger than
+    /// `2^24`, including those which don't fit in an `isize`, are split into `high * 2^24 + low`,
+    /// where `high` is built up in a counter cell and multiplied by `2^24` using small
+    /// multiplication loops before being added to this cell.
+    fn add_value(&mut self, value: T, negate: bool) {
+        /// The largest magnitude which is added directly with `.add_constant()`.
+        const MAX_DIRECT_AMOUNT: usize = 1 << 24;
+
+        if let Some(amount) = value
+            .try_into_isize()
+            .filter(|amount| amount.unsigned_abs() <= MAX_DIRECT_AMOUNT)
+        {
+            self.add_constant(if negate { -amount } else { amount });
+            return;
+        }
+
+        let (high, low) = value.split_isize();
+        let negative = is_negative(high);
+
+        // The counter holds the magnitude of `high`, so each loop below runs a finite number of
+        // times even if `T` doesn't wrap.
+        let mut counter = self.builder.cell(T::ZERO);
+        counter.add_value(high, negative);
+
+        // Five loops which each multiply by 16, followed by a loop which adds 16 to this cell for
+        // each count, multiply `high` by 2^24. Multiplying in the final loop means the counter
+        // never holds `high * 2^24`, which may not fit in `T` even when this cell's value does.
+        for _ in 0..5 {
+            let mut next = self.builder.cell(T::ZERO);
+
+            counter.while_nonzero_mut(|counter| {
+                counter.dec();
+                next.add_constant(16);
+            });
+
+            counter = next;
+        }
+
+        counter.while_nonzero_mut(|counter| {
+            counter.dec();
+            self.add_constant(if negative != negate { -16 } else { 16 });
+        });
+
+        self.add_constant(if negate { -low } else { low });
+    }
+
     /// Swaps the values of two cells.
     pub fn swap(&mut self, other: &mut Cell<N, T>) {
         let temp = self.move_and_zero();
@@ -108,9 +596,41 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
         temp.move_into(other);
     }
 
+    /// Swaps the values of two cells, using `scratch` as temporary storage instead of allocating a
+    /// new cell. `scratch` must be zero beforehand, and is left as zero afterwards, so one scratch
+    /// cell can be shared across many swaps without emitting code to allocate and clear it each
+    /// time.
+    pub fn swap_with_scratch(&mut self, other: &mut Cell<N, T>, scratch: &mut Cell<N, T>) {
+        self.add_into_all_and_zero([scratch]);
+        other.add_into_all_and_zero([self]);
+        scratch.add_into_all_and_zero([other]);
+    }
+
+    /// Swaps the values of this cell and `other` if `other` is smaller, so that this cell ends up
+    /// holding the smaller value. Values are compared as unsigned numbers.
+    pub fn sort_pair(&mut self, other: &mut Cell<N, T>) {
+        let mut other_is_smaller = other.lt(self);
+        other_is_smaller.if_nonzero_and_zero(|| self.swap(other));
+    }
+
+    /// Sorts up to four cells in ascending order using an optimal sorting network, which generates
+    /// less code than a general sort. Values are compared as unsigned numbers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `U` is greater than 4.
+    pub fn sort_small<const U: usize>(cells: &mut [Cell<N, T>; U]) {
+        for &(a, b) in sorting_network(U) {
+            let (low, high) = cells.split_at_mut(b);
+            low[a].sort_pair(&mut high[0]);
+        }
+    }
+
     /// Turns this cell into several new cells that are copies of the original, and destroys the
     /// original. If you need to keep the original cell intact after copying, use `.copy()` instead.
     pub fn into_copies<const U: usize>(mut self) -> [Cell<'a, N, T>; U] {
+        let _op = self.builder.log_op(OpKind::Copy);
+
         let mut cells = [(); U].map(|_| self.builder.cell(T::ZERO));
 
         self.while_nonzero_mut(|this| {
@@ -125,6 +645,8 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
     /// `.into_copies()` when possible, as it generates much shorter code by not needing a temporary
     /// cell.
     pub fn copy<const U: usize>(&self) -> [Cell<'a, N, T>; U] {
+        let _op = self.builder.log_op(OpKind::Copy);
+
         let mut cells = [(); U].map(|_| self.builder.cell(T::ZERO));
         let mut temp = self.builder.cell(T::ZERO);
 
@@ -163,6 +685,14 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
         output
     }
 
+    /// Moves the contents of this cell into a new cell and sets this cell to `value`. Returns the
+    /// new cell, which holds the previous value of this cell.
+    pub fn replace(&mut self, value: T) -> Cell<'a, N, T> {
+        let previous = self.move_and_zero();
+        *self += value;
+        previous
+    }
+
     /// Moves the contents of this cell into another cell, zeroing this cell afterwards.
     pub fn move_into_and_zero(&mut self, output: &mut Cell<N, T>) {
         output.zero();
@@ -175,9 +705,120 @@ impl<'a, const N: usize, T: CellValue> Cell<'a, N, T> {
     }
 }
 
+impl<'a, const N: usize, T: SignedCellValue> Cell<'a, N, T> {
+    /// Negates the value of this cell if `cond` is nonzero. `cond` is left intact.
+    pub fn negate_if(&mut self, cond: &Cell<N, T>) {
+        cond.if_nonzero(|| self.negate());
+    }
+
+    /// Creates a new cell containing 1 if the value of this cell is negative and 0 otherwise. The
+    /// value of this cell is preserved.
+    ///
+    /// Cells don't know their own sign, so this counts up and down from the value of this cell at
+    /// the same time and checks which direction reaches zero first. This takes time proportional to
+    /// the magnitude of the value.
+    pub fn is_negative(&self) -> Cell<'a, N, T> {
+        let mut result = self.builder.cell(T::ZERO);
+        let [mut up, mut down] = self.copy();
+        let mut searching = self.normalized();
+
+        searching.while_nonzero_mut(|searching| {
+            up.inc();
+            down.dec();
+
+            let mut up_reached_zero = up.not();
+            up_reached_zero.if_nonzero_and_zero(|| {
+                result.inc();
+                searching.zero();
+            });
+
+            let mut down_reached_zero = down.not();
+            down_reached_zero.if_nonzero_and_zero(|| searching.zero());
+        });
+
+        result
+    }
+
+    /// Sets this cell to its absolute value. As with Rust's wrapping integers, the absolute value
+    /// of the minimum value of a type is itself.
+    pub fn abs(&mut self) {
+        let is_negative = self.is_negative();
+        self.negate_if(&is_negative);
+    }
+}
+
+impl<'a, const N: usize> Cell<'a, N, Wrapping<u8>> {
+    /// Creates a new cell containing the base 2 logarithm of this cell, rounded down, which is the
+    /// index of its highest set bit. The logarithm of zero is returned as zero. The value of this
+    /// cell is preserved.
+    pub fn ilog2(&self) -> Cell<'a, N, Wrapping<u8>> {
+        let mut result = self.builder.cell(Wrapping(0));
+        let [mut value] = self.copy();
+        value /= Wrapping(2);
+
+        value.while_nonzero_mut(|value| {
+            result.inc();
+            *value /= Wrapping(2);
+        });
+
+        result
+    }
+
+    /// Writes the bits of this cell as eight ASCII `0` and `1` characters, starting with the most
+    /// significant bit. The value of this cell is preserved.
+    pub fn write_binary(&self) {
+        let [mut value] = self.copy();
+
+        let mut bits: Vec<_> = (0..8)
+            .map(|_| {
+                let bit = &value % Wrapping(2);
+                value /= Wrapping(2);
+                bit
+            })
+            .collect();
+
+        for bit in bits.iter_mut().rev() {
+            *bit += Wrapping(b'0');
+            bit.write();
+        }
+    }
+
+    /// Writes the value of this cell as a base-10 number made of ASCII digits, without leading
+    /// zeros. The value of this cell is preserved.
+    pub fn write_decimal(&self) {
+        let [mut value] = self.copy();
+
+        let mut ones = &value % Wrapping(10);
+        value /= Wrapping(10);
+        let mut tens = &value % Wrapping(10);
+        value /= Wrapping(10);
+        let mut hundreds = value;
+
+        // The tens digit is only a leading zero if the hundreds digit is also zero.
+        let mut has_tens = hundreds.or(&tens);
+
+        hundreds.if_nonzero_mut(|hundreds| {
+            *hundreds += Wrapping(b'0');
+            hundreds.write();
+        });
+
+        has_tens.if_nonzero_and_zero(|| {
+            tens += Wrapping(b'0');
+            tens.write();
+        });
+
+        ones += Wrapping(b'0');
+        ones.write();
+    }
+}
+
 impl<'a, const N: usize, T: CellValue> Drop for Cell<'a, N, T> {
     fn drop(&mut self) {
-        self.zero();
+        // Moving to this cell may panic if the pointer position is unknown, so avoid generating code
+        // when we're already unwinding from a panic.
+        if !std::thread::panicking() {
+            self.zero();
+        }
 
         let mut allocations = self.builder.allocations.borrow_mut();
         allocations[self.location] = false;
@@ -195,6 +836,8 @@ impl<'a, const N: usize, T: CellValue> Clone for Cell<'a, N, T> {
     }
 
     fn clone_from(&mut self, other: &Self) {
+        let _op = self.builder.log_op(OpKind::Copy);
+
         let temp = self.builder.cell(T::ZERO);
         let source = &self.builder.source;
 
@@ -226,6 +869,8 @@ impl<'a, const N: usize, T: CellValue> fmt::Debug for Cell<'a, N, T> {
 
 impl<'a, const N: usize, T: CellValue> ops::DivAssign<&Cell<'a, N, T>> for Cell<'a, N, T> {
     fn div_assign(&mut self, rhs: &Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Div);
+
         let mut temp0 = self.builder.cell(T::ZERO);
         let mut temp1 = self.builder.cell(T::ZERO);
         let mut temp2 = self.builder.cell(T::ZERO);
@@ -281,26 +926,16 @@ impl<'a, const N: usize, T: CellValue> ops::DivAssign<&Cell<'a, N, T>> for Cell<
 
 impl<'a, const N: usize, T: CellValue> ops::AddAssign<T> for Cell<'a, N, T> {
     fn add_assign(&mut self, rhs: T) {
-        self.goto();
+        let _op = self.builder.log_op(OpKind::Add);
 
-        let mut source = self.builder.source.borrow_mut();
-
-        let size = rhs.into_isize();
-
-        if size < 0 {
-            for _ in size..0 {
-                source.push('-');
-            }
-        } else {
-            for _ in 0..size {
-                source.push('+');
-            }
-        }
+        self.add_value(rhs, false);
     }
 }
 
 impl<'a, const N: usize, T: CellValue> ops::AddAssign<&Cell<'a, N, T>> for Cell<'a, N, T> {
     fn add_assign(&mut self, rhs: &Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Add);
+
         let temp = rhs.builder.cell(T::ZERO);
         let source = &rhs.builder.source;
 
@@ -324,6 +959,8 @@ impl<'a, const N: usize, T: CellValue> ops::AddAssign<&Cell<'a, N, T>> for Cell<
 
 impl<'a, const N: usize, T: CellValue> ops::AddAssign<Cell<'a, N, T>> for Cell<'a, N, T> {
     fn add_assign(&mut self, mut rhs: Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Add);
+
         rhs.while_nonzero_mut(|rhs| {
             rhs.dec();
             self.inc();
@@ -385,26 +1022,16 @@ impl<'a, const N: usize, T: CellValue> ops::Add<T> for &Cell<'a, N, T> {
 
 impl<'a, const N: usize, T: CellValue> ops::SubAssign<T> for Cell<'a, N, T> {
     fn sub_assign(&mut self, rhs: T) {
-        self.goto();
-
-        let mut source = self.builder.source.borrow_mut();
-
-        let size = rhs.into_isize();
+        let _op = self.builder.log_op(OpKind::Sub);
 
-        if size < 0 {
-            for _ in size..0 {
-                source.push('+');
-            }
-        } else {
-            for _ in 0..size {
-                source.push('-');
-            }
-        }
+        self.add_value(rhs, true);
     }
 }
 
 impl<'a, const N: usize, T: CellValue> ops::SubAssign<&Cell<'a, N, T>> for Cell<'a, N, T> {
     fn sub_assign(&mut self, rhs: &Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Sub);
+
         let temp = rhs.builder.cell(T::ZERO);
         let source = &rhs.builder.source;
 
@@ -428,6 +1055,8 @@ impl<'a, const N: usize, T: CellValue> ops::SubAssign<&Cell<'a, N, T>> for Cell<
 
 impl<'a, const N: usize, T: CellValue> ops::SubAssign<Cell<'a, N, T>> for Cell<'a, N, T> {
     fn sub_assign(&mut self, mut rhs: Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Sub);
+
         rhs.while_nonzero_mut(|rhs| {
             rhs.dec();
             self.dec();
@@ -488,6 +1117,8 @@ impl<'a, const N: usize, T: CellValue> ops::Sub<T> for &Cell<'a, N, T> {
 
 impl<'a, const N: usize, T: CellValue> ops::MulAssign<&Cell<'a, N, T>> for Cell<'a, N, T> {
     fn mul_assign(&mut self, rhs: &Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Mul);
+
         let mut x = self.move_and_zero();
 
         x.while_nonzero_mut(|x| {
@@ -499,13 +1130,22 @@ impl<'a, const N: usize, T: CellValue> ops::MulAssign<&Cell<'a, N, T>> for Cell<
 
 impl<'a, const N: usize, T: CellValue> ops::MulAssign<Cell<'a, N, T>> for Cell<'a, N, T> {
     fn mul_assign(&mut self, rhs: Cell<'a, N, T>) {
-        *self += &rhs;
+        *self *= &rhs;
     }
 }
 
 impl<'a, const N: usize, T: CellValue> ops::MulAssign<T> for Cell<'a, N, T> {
     fn mul_assign(&mut self, rhs: T) {
-        *self *= &self.builder().cell(rhs);
+        let _op = self.builder.log_op(OpKind::Mul);
+
+        // Adding the constant directly lets `add_constant` pick a short encoding for it, and avoids
+        // copying a cell holding the constant on every iteration.
+        let mut x = self.move_and_zero();
+
+        x.while_nonzero_mut(|x| {
+            *self += rhs;
+            x.dec();
+        });
     }
 }
 
@@ -623,3 +1263,807 @@ impl<'a, const N: usize, T: CellValue> ops::Div<T> for &Cell<'a, N, T> {
         self.clone() / rhs
     }
 }
+
+impl<'a, const N: usize, T: CellValue> ops::RemAssign<&Cell<'a, N, T>> for Cell<'a, N, T> {
+    fn rem_assign(&mut self, rhs: &Cell<'a, N, T>) {
+        let _op = self.builder.log_op(OpKind::Rem);
+
+        let mut value = self.move_and_zero();
+        let [mut countdown] = rhs.copy();
+
+        value.while_nonzero_mut(|value| {
+            value.dec();
+            self.inc();
+            countdown.dec();
+
+            let mut wrapped = countdown.not();
+
+            wrapped.while_nonzero_mut(|wrapped| {
+                self.zero();
+                countdown += rhs;
+                wrapped.zero();
+            });
+        });
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::RemAssign<T> for Cell<'a, N, T> {
+    fn rem_assign(&mut self, rhs: T) {
+        *self %= &self.builder().cell(rhs);
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::RemAssign<Cell<'a, N, T>> for Cell<'a, N, T> {
+    fn rem_assign(&mut self, rhs: Cell<'a, N, T>) {
+        *self %= &rhs;
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<Cell<'a, N, T>> for Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(mut self, rhs: Cell<'a, N, T>) -> Self::Output {
+        self %= rhs;
+        self
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<&Cell<'a, N, T>> for Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(mut self, rhs: &Cell<'a, N, T>) -> Self::Output {
+        self %= rhs;
+        self
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<Cell<'a, N, T>> for &Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(self, rhs: Cell<'a, N, T>) -> Self::Output {
+        self.clone() % rhs
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<&Cell<'a, N, T>> for &Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(self, rhs: &Cell<'a, N, T>) -> Self::Output {
+        self.clone() % rhs
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<T> for Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(mut self, rhs: T) -> Self::Output {
+        self %= rhs;
+        self
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> ops::Rem<T> for &Cell<'a, N, T> {
+    type Output = Cell<'a, N, T>;
+
+    fn rem(self, rhs: T) -> Self::Output {
+        self.clone() % rhs
+    }
+}
+
+/// Checks whether a value is negative, splitting it with `.split_isize()` until it fits in an
+/// `isize` if needed.
+fn is_negative<T: CellValue>(value: T) -> bool {
+    match value.try_into_isize() {
+        Some(value) => value < 0,
+        None => is_negative(value.split_isize().0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the program built so far and returns its output as bytes.
+    fn output<const N: usize>(builder: &Builder<N, Wrapping<u8>>) -> Vec<u8> {
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        output.into_iter().map(|value| value.0).collect()
+    }
+
+    #[test]
+    fn negate_if_only_negates_when_cond_is_nonzero() {
+        let builder = Builder::<16, Wrapping<i8>>::new();
+        let mut value = builder.cell(Wrapping(-5));
+        let cond = builder.cell(Wrapping(1));
+        value.negate_if(&cond);
+        value.write();
+        cond.write();
+
+        let mut value = builder.cell(Wrapping(-5));
+        let cond = builder.cell(Wrapping(0));
+        value.negate_if(&cond);
+        value.write();
+        cond.write();
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [5, 1, -5, 0].map(Wrapping));
+    }
+
+    #[test]
+    fn parity_matches_low_bit() {
+        let values = [0, 1, 2, 7, 128, 255];
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for value in values {
+            let cell = builder.cell(Wrapping(value));
+            cell.parity().write();
+            cell.write();
+        }
+
+        let expected: Vec<u8> = values
+            .iter()
+            .flat_map(|value| [value & 1, *value])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn if_zero_runs_only_for_zero_cells() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let marker = builder.cell(Wrapping(b'z'));
+
+        for value in [0, 1, 200] {
+            let cell = builder.cell(Wrapping(value));
+            cell.if_zero(|| marker.write());
+            cell.write();
+        }
+
+        assert_eq!(output(&builder), [b'z', 0, 1, 200]);
+    }
+
+    #[test]
+    fn map_through_maps_listed_values_and_keeps_others() {
+        let mapping = b"abc".map(|char| (Wrapping(char), Wrapping(char.to_ascii_uppercase())));
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for char in *b"abcz!" {
+            let mut cell = builder.cell(Wrapping(char));
+            cell.map_through(&mapping);
+            cell.write();
+        }
+
+        assert_eq!(output(&builder), b"ABCz!");
+    }
+
+    #[test]
+    fn map_through_applies_only_one_mapping() {
+        let mapping = [(b'a', b'b'), (b'b', b'c')].map(|(from, to)| (Wrapping(from), Wrapping(to)));
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for char in *b"ab" {
+            let mut cell = builder.cell(Wrapping(char));
+            cell.map_through(&mapping);
+            cell.write();
+        }
+
+        assert_eq!(output(&builder), b"bc");
+    }
+
+    #[test]
+    fn ilog2_finds_highest_set_bit() {
+        let values = [0, 1, 2, 3, 8, 100, 128, 255];
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for value in values {
+            let cell = builder.cell(Wrapping(value));
+            cell.ilog2().write();
+            cell.write();
+        }
+
+        let expected: Vec<u8> = values
+            .iter()
+            .flat_map(|&value| [value.checked_ilog2().unwrap_or(0) as u8, value])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn eq_is_one_exactly_when_cells_match() {
+        let pairs = [(0, 0), (3, 3), (3, 4), (4, 3), (0, 255)];
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for (a, b) in pairs {
+            let a = builder.cell(Wrapping(a));
+            let b = builder.cell(Wrapping(b));
+            a.eq(&b).write();
+        }
+
+        let expected: Vec<u8> = pairs.iter().map(|(a, b)| (a == b) as u8).collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn rem_leaves_remainder_in_memory() {
+        let pairs = [(17, 5), (20, 5), (4, 9), (200, 1), (255, 16), (0, 3)];
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        let mut cells = Vec::new();
+
+        for (a, b) in pairs {
+            let mut by_cell = builder.cell(Wrapping(a));
+            by_cell %= &builder.cell(Wrapping(b));
+
+            let mut by_constant = builder.cell(Wrapping(a));
+            by_constant %= Wrapping(b);
+
+            let by_value = builder.cell(Wrapping(a)) % builder.cell(Wrapping(b));
+            cells.push([by_cell, by_constant, by_value]);
+        }
+
+        let runner = builder.run(std::iter::empty(), Vec::new()).unwrap();
+
+        for ((a, b), cells) in pairs.into_iter().zip(&cells) {
+            for cell in cells {
+                assert_eq!(runner.cell_value(cell.location()), Wrapping(a % b));
+            }
+        }
+    }
+
+    #[test]
+    fn eq_handles_wrapping_and_preserves_operands() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        let mut a = builder.cell(Wrapping(255));
+        a.inc();
+        let b = builder.cell(Wrapping(0));
+        a.eq(&b).write();
+        a.write();
+        b.write();
+
+        let c = builder.cell(Wrapping(1));
+        let d = builder.cell(Wrapping(255));
+        c.eq(&d).write();
+        c.write();
+        d.write();
+
+        assert_eq!(output(&builder), [1, 0, 0, 0, 1, 255]);
+    }
+
+    #[test]
+    fn comparisons_match_rust_on_a_grid() {
+        let values = [0, 1, 2, 127, 128, 254, 255];
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut expected = Vec::new();
+
+        for a in values {
+            for b in values {
+                let x = builder.cell(Wrapping(a));
+                let y = builder.cell(Wrapping(b));
+                x.lt(&y).write();
+                x.gt(&y).write();
+                x.le(&y).write();
+                x.ge(&y).write();
+                x.write();
+                y.write();
+                expected.extend([a < b, a > b, a <= b, a >= b].map(u8::from));
+                expected.extend([a, b]);
+            }
+        }
+
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn lt_and_gt_work_for_plain_u8() {
+        let pairs = [(5, 5), (0, 0), (0, 9), (9, 0), (255, 254)];
+        let builder = Builder::<16, u8>::new();
+
+        for (a, b) in pairs {
+            let x = builder.cell(a);
+            let y = builder.cell(b);
+            x.lt(&y).write();
+            x.gt(&y).write();
+        }
+
+        let expected: Vec<u8> = pairs
+            .iter()
+            .flat_map(|(a, b)| [a < b, a > b].map(u8::from))
+            .collect();
+        assert_eq!(builder.run_to_vec(std::iter::empty()).unwrap(), expected);
+    }
+
+    #[test]
+    fn not_collapses_nonzero_values_and_preserves_cell() {
+        let values = [0, 1, 2, 100, 255];
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for value in values {
+            let cell = builder.cell(Wrapping(value));
+            cell.not().write();
+            cell.write();
+        }
+
+        let expected: Vec<u8> = values
+            .iter()
+            .flat_map(|&value| [u8::from(value == 0), value])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn eq_const_compares_against_constant() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let cell = builder.cell(Wrapping(42));
+        cell.eq_const(Wrapping(42)).write();
+        cell.eq_const(Wrapping(41)).write();
+        cell.eq_const(Wrapping(0)).write();
+        cell.write();
+
+        let zero = builder.cell(Wrapping(0));
+        zero.eq_const(Wrapping(0)).write();
+
+        assert_eq!(output(&builder), [1, 0, 0, 42, 1]);
+    }
+
+    #[test]
+    fn if_nonzero_and_if_else_pick_the_right_branch() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let then = builder.cell(Wrapping(b't'));
+        let els = builder.cell(Wrapping(b'e'));
+
+        for value in [0, 1, 200] {
+            let cell = builder.cell(Wrapping(value));
+            cell.if_nonzero(|| then.write());
+            cell.if_else(|| then.write(), || els.write());
+            cell.write();
+        }
+
+        let outer = builder.cell(Wrapping(3));
+        let inner = builder.cell(Wrapping(0));
+        outer.if_else(
+            || inner.if_else(|| then.write(), || els.write()),
+            || then.write(),
+        );
+
+        assert_eq!(
+            output(&builder),
+            [b'e', 0, b't', b't', 1, b't', b't', 200, b'e']
+        );
+    }
+
+    #[test]
+    fn not_maps_0_1_200_to_1_0_0() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for value in [0, 1, 200] {
+            builder.cell(Wrapping(value)).not().write();
+        }
+
+        assert_eq!(output(&builder), [1, 0, 0]);
+    }
+
+    #[test]
+    fn and_or_truth_table_normalizes_large_values() {
+        let values = [0, 1, 200];
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut expected = Vec::new();
+
+        for lhs in values {
+            for rhs in values {
+                let a = builder.cell(Wrapping(lhs));
+                let b = builder.cell(Wrapping(rhs));
+                a.and(&b).write();
+                a.or(&b).write();
+                a.write();
+                b.write();
+                expected.extend([
+                    u8::from(lhs != 0 && rhs != 0),
+                    u8::from(lhs != 0 || rhs != 0),
+                    lhs,
+                    rhs,
+                ]);
+            }
+        }
+
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn destructive_conditionals_run_exactly_one_branch() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let then = builder.cell(Wrapping(b't'));
+        let els = builder.cell(Wrapping(b'e'));
+
+        for value in [0, 1, 200, 255] {
+            let mut flag = builder.cell(Wrapping(value));
+            flag.if_nonzero_and_zero(|| then.write());
+            flag.write();
+
+            let mut flag = builder.cell(Wrapping(value));
+            flag.if_else_and_zero(|| then.write(), || els.write());
+            flag.write();
+        }
+
+        assert_eq!(output(&builder), *b"\0e\0t\0t\0t\0t\0t\0t\0",);
+    }
+
+    #[test]
+    fn and_or_compose_with_if_nonzero() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let yes = builder.cell(Wrapping(b'y'));
+        let no = builder.cell(Wrapping(b'n'));
+
+        for (lhs, rhs) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+            let a = builder.cell(Wrapping(lhs));
+            let b = builder.cell(Wrapping(rhs));
+            a.and(&b).if_else(|| yes.write(), || no.write());
+            a.or(&b).if_else(|| yes.write(), || no.write());
+            a.or(&b).if_nonzero(|| yes.write());
+        }
+
+        assert_eq!(output(&builder), *b"nnnyynyyyyy");
+    }
+
+    #[test]
+    fn countdown_capped_runs_min_of_self_and_cap_times() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mark = builder.cell(Wrapping(b'*'));
+
+        for (value, cap) in [(10, 3), (2, 5), (0, 4), (4, 0)] {
+            let mut counter = builder.cell(Wrapping(value));
+            let cap = builder.cell(Wrapping(cap));
+            counter.countdown_capped(&cap, || mark.write());
+            counter.write();
+            cap.write();
+        }
+
+        assert_eq!(output(&builder), *b"***\x07\x03**\0\x05\0\x04\x04\0");
+    }
+
+    #[test]
+    fn to_bool_normalizes_in_place() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+
+        for value in [0, 5, 255] {
+            let mut cell = builder.cell(Wrapping(value));
+            cell.to_bool();
+            cell.write();
+        }
+
+        assert_eq!(output(&builder), [0, 1, 1]);
+    }
+
+    #[test]
+    fn min_and_max_match_rust() {
+        let pairs = [(3, 7), (7, 3), (5, 5), (0, 255), (200, 100)];
+        let builder = Builder::<32, Wrapping<u8>>::new();
+
+        for (lhs, rhs) in pairs {
+            let a = builder.cell(Wrapping(lhs));
+            let b = builder.cell(Wrapping(rhs));
+            a.min(&b).write();
+            a.max(&b).write();
+        }
+
+        let expected: Vec<u8> = pairs
+            .iter()
+            .flat_map(|&(lhs, rhs)| [lhs.min(rhs), lhs.max(rhs)])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn add_assign_large_constants_match_unary_form() {
+        for amount in (0..=255).step_by(7) {
+            let builder = Builder::<8, Wrapping<u8>>::new();
+            let mut cell = builder.cell(Wrapping(3));
+            cell += Wrapping(amount);
+            cell.write();
+
+            let naive =
+                crate::program::Program::from(&*format!("+++{}.", "+".repeat(amount.into())));
+            let expected = naive
+                .run::<8, _, _, u8>(std::iter::empty(), Vec::new())
+                .into_output();
+
+            assert_eq!(output(&builder), expected);
+
+            if amount > 32 {
+                assert!(builder.source().len() < usize::from(amount));
+            }
+        }
+    }
+
+    #[test]
+    fn large_cells_use_short_source() {
+        let builder = Builder::<8, Wrapping<u8>>::new();
+        let cell = builder.cell(Wrapping(250));
+        assert!(builder.source().len() < 250 / 4);
+
+        cell.write();
+        assert_eq!(output(&builder), [250]);
+    }
+
+    #[test]
+    fn abs_of_signed_cells() {
+        let builder = Builder::<16, Wrapping<i8>>::new();
+
+        for value in [-5, 5, 0, -128] {
+            let mut cell = builder.cell(Wrapping(value));
+            cell.abs();
+            cell.write();
+        }
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [5, 5, 0, -128].map(Wrapping));
+    }
+
+    #[test]
+    fn pow_preserves_operands() {
+        let builder = Builder::<16, Wrapping<u16>>::new();
+        let base = builder.cell(Wrapping(3));
+        let exponent = builder.cell(Wrapping(4));
+        base.pow(&exponent).write();
+        base.write();
+        exponent.write();
+
+        let zero = builder.cell(Wrapping(0));
+        base.pow(&zero).write();
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [81, 3, 4, 1].map(Wrapping));
+    }
+
+    #[test]
+    fn mul_mod_preserves_operands() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let a = builder.cell(Wrapping(7));
+        let b = builder.cell(Wrapping(7));
+        let modulus = builder.cell(Wrapping(10));
+        a.mul_mod(&b, &modulus).write();
+        a.write();
+        b.write();
+        modulus.write();
+
+        // 200 * 200 overflows a `u8`, but reducing as it goes keeps the result exact.
+        let large = builder.cell(Wrapping(200));
+        let modulus = builder.cell(Wrapping(7));
+        large.mul_mod(&large, &modulus).write();
+
+        assert_eq!(output(&builder), [9, 7, 7, 10, (200 * 200 % 7) as u8]);
+    }
+
+    #[test]
+    fn bool_cells_saturate_at_true_and_false() {
+        let builder = Builder::<8, bool>::new();
+        let mut cell = builder.cell(false);
+        cell.add_constant(100);
+        cell.write();
+        cell.dec();
+        cell.write();
+        cell.inc();
+        cell.inc();
+        cell.dec();
+        cell.write();
+        cell += true;
+        cell.write();
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [true, false, false, true]);
+    }
+
+    #[test]
+    fn in_range_is_inclusive() {
+        let values = [0, 9, 10, 15, 20, 21, 255];
+        let builder = Builder::<32, Wrapping<u8>>::new();
+
+        for value in values {
+            let cell = builder.cell(Wrapping(value));
+            cell.in_range(Wrapping(10), Wrapping(20)).write();
+            cell.write();
+        }
+
+        let expected: Vec<u8> = values
+            .iter()
+            .flat_map(|&value| [u8::from((10..=20).contains(&value)), value])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn set_uses_short_source_for_large_values() {
+        let builder = Builder::<8, Wrapping<u8>>::new();
+        let mut cell = builder.cell(Wrapping(3));
+        let before = builder.source().len();
+        cell.set(Wrapping(100));
+        assert!(builder.source().len() - before < 100 / 2);
+
+        cell.write();
+        assert_eq!(output(&builder), [100]);
+    }
+
+    #[test]
+    fn mul_assign_constant_is_shorter_than_multiplying_by_a_cell() {
+        let by_constant = Builder::<16, Wrapping<u8>>::new();
+        let mut cell = by_constant.cell(Wrapping(3));
+        let start = by_constant.source().len();
+        cell *= Wrapping(200);
+        let constant_length = by_constant.source().len() - start;
+        cell.write();
+
+        let by_cell = Builder::<16, Wrapping<u8>>::new();
+        let mut cell = by_cell.cell(Wrapping(3));
+        let start = by_cell.source().len();
+        cell *= by_cell.cell(Wrapping(200));
+        let cell_length = by_cell.source().len() - start;
+        cell.write();
+
+        assert!(constant_length < cell_length);
+        assert_eq!(output(&by_constant), [(3 * 200) as u8]);
+        assert_eq!(output(&by_cell), [(3 * 200) as u8]);
+    }
+
+    #[test]
+    fn swap_with_scratch_is_shorter_than_swap() {
+        fn swaps(shared_scratch: bool) -> (usize, Vec<u8>) {
+            let builder = Builder::<16, Wrapping<u8>>::new();
+            let mut a = builder.cell(Wrapping(1));
+            let mut b = builder.cell(Wrapping(2));
+            let mut scratch = builder.cell(Wrapping(0));
+            let start = builder.source().len();
+
+            for _ in 0..5 {
+                if shared_scratch {
+                    a.swap_with_scratch(&mut b, &mut scratch);
+                } else {
+                    a.swap(&mut b);
+                }
+            }
+
+            let length = builder.source().len() - start;
+            a.write();
+            b.write();
+            scratch.write();
+            (length, output(&builder))
+        }
+
+        let (shared_length, shared_output) = swaps(true);
+        let (swap_length, swap_output) = swaps(false);
+        assert!(shared_length < swap_length);
+        assert_eq!(shared_output, [2, 1, 0]);
+        assert_eq!(swap_output, [2, 1, 0]);
+    }
+
+    #[test]
+    fn write_binary_prints_bits_most_significant_first() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let cell = builder.cell(Wrapping(0b1010_0101));
+        cell.write_binary();
+        cell.write();
+        builder.cell(Wrapping(1)).write_binary();
+
+        assert_eq!(output(&builder), *b"10100101\xa500000001");
+    }
+
+    #[test]
+    fn add_assign_constants_larger_than_isize() {
+        let builder = Builder::<16, Wrapping<u128>>::new();
+        let large = Wrapping(isize::MAX as u128 * 4 + 123);
+        let mut cell = builder.cell(Wrapping(5));
+        cell += large;
+        let location = cell.location();
+        let mut other = builder.cell(large);
+        other -= large;
+        let other_location = other.location();
+
+        assert!(builder.source().len() < 10_000);
+
+        let runner = builder
+            .compile_optimized()
+            .unwrap()
+            .run::<16, _, _, Wrapping<u128>>(std::iter::empty(), Vec::new());
+        assert_eq!(runner.cell_value(location), large + Wrapping(5));
+        assert_eq!(runner.cell_value(other_location), Wrapping(0));
+    }
+
+    #[test]
+    fn compare_gives_three_way_ordering() {
+        let pairs = [(3, 7), (5, 5), (7, 3), (0, 255), (255, 0)];
+        let builder = Builder::<32, Wrapping<u8>>::new();
+
+        for (lhs, rhs) in pairs {
+            let a = builder.cell(Wrapping(lhs));
+            let b = builder.cell(Wrapping(rhs));
+            a.compare(&b).write();
+            a.write();
+            b.write();
+        }
+
+        let expected: Vec<u8> = pairs
+            .iter()
+            .flat_map(|&(lhs, rhs)| [(lhs.cmp(&rhs) as i8 + 1) as u8, lhs, rhs])
+            .collect();
+        assert_eq!(output(&builder), expected);
+    }
+
+    #[test]
+    fn write_decimal_matches_to_string_for_every_byte() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let value = builder.read();
+        value.write_decimal();
+        value.write();
+        let program = builder.compile_optimized().unwrap();
+
+        for byte in 0..=255u8 {
+            let runner = program.run::<32, _, _, _>([Wrapping(byte)].into_iter(), Vec::new());
+            let mut expected = byte.to_string().into_bytes();
+            expected.push(byte);
+            let output: Vec<u8> = runner.output().iter().map(|value| value.0).collect();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn replace_returns_previous_value() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut cell = builder.read();
+        let previous = cell.replace(Wrapping(9));
+        cell.write();
+        previous.write();
+
+        let mut cell = builder.cell(Wrapping(200));
+        let previous = cell.replace(Wrapping(0));
+        cell.write();
+        previous.write();
+
+        let output = builder.run_to_vec([Wrapping(4)].into_iter()).unwrap();
+        assert_eq!(output, [9, 4, 0, 200].map(Wrapping));
+    }
+
+    #[test]
+    fn sort_small_uses_optimal_networks() {
+        let sizes: Vec<_> = (0..=4).map(|len| sorting_network(len).len()).collect();
+        assert_eq!(sizes, [0, 0, 1, 3, 5]);
+
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut cells = [(); 4].map(|_| builder.read());
+        Cell::sort_small(&mut cells);
+        cells.iter().for_each(|cell| cell.write());
+        let program = builder.compile_optimized().unwrap();
+
+        let sort = |input: [u8; 4]| {
+            let runner = program.run::<32, _, _, _>(input.map(Wrapping).into_iter(), Vec::new());
+            runner
+                .output()
+                .iter()
+                .map(|value| value.0)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sort([200, 50, 7, 0]), [0, 7, 50, 200]);
+        assert_eq!(sort([9, 3, 9, 3]), [3, 3, 9, 9]);
+
+        for a in 0..4u8 {
+            for b in 0..4 {
+                for c in 0..4 {
+                    for d in 0..4 {
+                        let mut expected = [a, b, c, d];
+                        expected.sort();
+                        assert_eq!(sort([a, b, c, d]), expected);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn write_decimal_writes_no_leading_zeros() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let space = builder.cell(Wrapping(b' '));
+
+        for value in [0, 7, 255] {
+            builder.cell(Wrapping(value)).write_decimal();
+            space.write();
+        }
+
+        assert_eq!(output(&builder), b"0 7 255 ");
+    }
+}
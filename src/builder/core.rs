@@ -1,9 +1,17 @@
 //! The core implementation details of the brainfuck allocator.
 
-use super::{cell::Cell, string::CellString, types::CellValue};
+use super::{
+    array::CellArray,
+    cell::Cell,
+    op_log::{OpGuard, OpKind, OpReport},
+    ring_buffer::RingBuffer,
+    stack::CellStack,
+    string::{CellString, CharWriter},
+    types::{CellValue, DebuggableCellValue},
+};
 use crate::{
-    program::Program,
-    runner::{output::RunnerOutput, Runner},
+    program::{ParseError, Program},
+    runner::{input::RunnerInput, output::RunnerOutput, Runner},
 };
 use std::{
     cell::RefCell,
@@ -20,9 +28,10 @@ use std::{
 /// currently pointed at cell is represented with an underline.
 pub struct Builder<const N: usize, T: CellValue> {
     pub(super) source: RefCell<String>,
-    pub(super) pointer: RefCell<usize>,
+    pub(super) pointer: RefCell<Option<usize>>,
     pub(super) allocations: RefCell<[bool; N]>,
     pub(super) lowest_unallocated_value: RefCell<usize>,
+    pub(super) op_log: RefCell<Option<Vec<OpReport>>>,
     _phantom: PhantomData<T>,
 }
 
@@ -31,33 +40,116 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
     pub fn new() -> Self {
         Builder {
             source: RefCell::new(String::new()),
-            pointer: RefCell::new(0),
+            pointer: RefCell::new(Some(0)),
             allocations: RefCell::new([false; N]),
             lowest_unallocated_value: RefCell::new(0),
+            op_log: RefCell::new(None),
             _phantom: PhantomData,
         }
     }
 
+    /// Clears this builder's source code and allocations, so that it can be reused to build another
+    /// program. All cells created by this builder must be dropped beforehand, since dropping them
+    /// afterwards would generate code and free cells which may have since been reallocated.
+    pub fn reset(&self) {
+        debug_assert!(
+            self.allocations.borrow().iter().all(|allocated| !allocated),
+            "cannot reset a builder while its cells are still alive"
+        );
+
+        self.source.borrow_mut().clear();
+        *self.pointer.borrow_mut() = Some(0);
+        *self.allocations.borrow_mut() = [false; N];
+        *self.lowest_unallocated_value.borrow_mut() = 0;
+
+        if let Some(log) = &mut *self.op_log.borrow_mut() {
+            log.clear();
+        }
+    }
+
+    /// Clears this builder's source code and allocations so that it can be reused to build another
+    /// program, without allocating a new builder. This is the same as `.reset()`, and has the same
+    /// requirement that every cell created by this builder is dropped beforehand.
+    pub fn clear(&self) {
+        self.reset();
+    }
+
+    /// Starts recording the operations performed by this builder, along with how much source code
+    /// each one generates. The recorded operations can be retrieved with `.op_report()`.
+    pub fn enable_op_log(&self) {
+        self.op_log.borrow_mut().get_or_insert_with(Vec::new);
+    }
+
+    /// Gets the operations recorded since `.enable_op_log()` was called, in the order they
+    /// finished. Returns an empty list if the operation log is not enabled.
+    pub fn op_report(&self) -> Vec<OpReport> {
+        self.op_log.borrow().clone().unwrap_or_default()
+    }
+
+    /// Starts recording an operation, which finishes once the returned guard is dropped.
+    pub(super) fn log_op(&self, kind: OpKind) -> OpGuard<'_, N, T> {
+        OpGuard::new(self, kind)
+    }
+
+    /// Gets a copy of the source code this builder has generated so far.
+    pub fn source(&self) -> String {
+        self.source.borrow().clone()
+    }
+
     /// Compiles this builder into a program.
-    pub fn compile(&self) -> Result<Program, &'static str> {
+    pub fn compile(&self) -> Result<Program, ParseError> {
         Program::new(self.source.borrow().as_str())
     }
 
+    /// Compiles this builder into a program, then runs it through `Program::optimize`, which folds
+    /// the long runs of `+`, `-`, `<`, and `>` the builder generates and replaces simple loops.
+    pub fn compile_optimized(&self) -> Result<Program, ParseError> {
+        Ok(self.compile()?.optimize().0)
+    }
+
     /// Compiles this builder and runs it on a given input.
-    pub fn run<I: Iterator<Item = T>, O: RunnerOutput<T>>(
+    pub fn run<I: RunnerInput<T>, O: RunnerOutput<T>>(
         &self,
         input: I,
         output: O,
-    ) -> Result<Runner<N, I, O, T>, &'static str> {
+    ) -> Result<Runner<N, I, O, T>, ParseError> {
         Ok(self.compile()?.run(input, output))
     }
 
+    /// Compiles and optimizes this builder, then runs it on a given input. Optimized programs write
+    /// the same output and leave memory in the same state, but usually run much faster.
+    pub fn run_optimized<I: RunnerInput<T>, O: RunnerOutput<T>>(
+        &self,
+        input: I,
+        output: O,
+    ) -> Result<Runner<N, I, O, T>, ParseError> {
+        Ok(self.compile_optimized()?.run(input, output))
+    }
+
+    /// Compiles this builder, runs it on a given input, and returns every value it wrote.
+    pub fn run_to_vec<I: RunnerInput<T>>(&self, input: I) -> Result<Vec<T>, ParseError> {
+        Ok(self.run(input, Vec::new())?.into_output())
+    }
+
+    /// Compiles this builder, runs it on a given input, and returns everything it wrote as a
+    /// string, where each value written is converted into a single character.
+    pub fn run_to_string<I: RunnerInput<T>>(&self, input: I) -> Result<String, ParseError>
+    where
+        T: DebuggableCellValue,
+    {
+        Ok(self
+            .run_to_vec(input)?
+            .into_iter()
+            .map(DebuggableCellValue::into_char)
+            .collect())
+    }
+
     /// Compiles this builder and runs it, using stdin and stdout as input and output respectively.
     pub fn run_interactive<I: FnMut(u8) -> T, O: FnMut(T) -> u8>(
         &self,
         input_adapter: I,
         output_adapter: O,
-    ) -> Result<Runner<N, impl Iterator<Item = T>, impl RunnerOutput<T>, T>, &'static str> {
+    ) -> Result<Runner<N, impl RunnerInput<T>, impl RunnerOutput<T>, T>, ParseError> {
         struct Output<T, O: FnMut(T) -> u8> {
             stdout: Stdout,
             adapter: O,
@@ -86,27 +178,71 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
     ///
     /// Make sure the cells are initialized before being passed to outside functions.
     pub unsafe fn array_uninit<const U: usize>(&self) -> [Cell<N, T>; U] {
+        unsafe { self.consecutive_uninit(U, 1) }.try_into().unwrap()
+    }
+
+    /// Creates an array of cells guaranteed to be consecutive in memory, where the first cell's
+    /// location is a multiple of `alignment`.
+    ///
+    /// ## Safety
+    ///
+    /// Make sure the cells are initialized before being passed to outside functions.
+    pub unsafe fn aligned_array_uninit<const U: usize>(
+        &self,
+        alignment: usize,
+    ) -> [Cell<'_, N, T>; U] {
+        unsafe { self.consecutive_uninit(U, alignment) }
+            .try_into()
+            .unwrap()
+    }
+
+    /// Creates a list of `len` cells guaranteed to be consecutive in memory, where the first cell's
+    /// location is a multiple of `alignment`. This is the same as `.aligned_array_uninit()`, but
+    /// for lengths which are only known at runtime.
+    ///
+    /// ## Safety
+    ///
+    /// Make sure the cells are initialized before being passed to outside functions.
+    pub(super) unsafe fn consecutive_uninit(
+        &self,
+        len: usize,
+        alignment: usize,
+    ) -> Vec<Cell<'_, N, T>> {
+        if alignment == 0 {
+            panic!("cannot align cells to a multiple of zero");
+        }
+
+        if len == 0 {
+            return Vec::new();
+        }
+
         let location = *self.lowest_unallocated_value.borrow();
         let mut allocations = self.allocations.borrow_mut();
 
         let Some(chunk_start) = allocations
-            .array_windows::<U>()
+            .windows(len)
             .enumerate()
             .skip(location)
+            .filter(|(index, _)| index % alignment == 0)
             .find(|(_, chunk)| chunk.iter().all(|value| !*value))
             .map(|x| x.0)
         else {
-            if U == 1 {
+            if alignment != 1 {
+                panic!(
+                    "{}",
+                    format!("not enough memory to allocate {len} consecutive cells aligned to {alignment}")
+                );
+            } else if len == 1 {
                 panic!("not enough memory to allocate 1 cell")
             } else {
                 panic!(
                     "{}",
-                    format!("not enough memory to allocate {U} consecutive cells")
+                    format!("not enough memory to allocate {len} consecutive cells")
                 );
             }
         };
 
-        for index in chunk_start..chunk_start + U {
+        for index in chunk_start..chunk_start + len {
             allocations[index] = true;
         }
 
@@ -114,14 +250,12 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
             if !allocations[next_location] {
                 self.lowest_unallocated_value.replace(next_location);
 
-                return (chunk_start..chunk_start + U)
+                return (chunk_start..chunk_start + len)
                     .map(|location| Cell {
                         builder: self,
                         location,
                     })
-                    .collect::<Vec<_>>()
-                    .try_into()
-                    .unwrap();
+                    .collect();
             }
         }
 
@@ -139,6 +273,48 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
         cells
     }
 
+    /// Creates a `CellArray` of initialized cells, which are guaranteed to be consecutive in memory
+    /// and directly preceded by the four scratch cells used for runtime indexing.
+    pub fn cell_array<const U: usize>(&self, value: [T; U]) -> CellArray<'_, N, U, T> {
+        let mut cells = unsafe { self.consecutive_uninit(U + 4, 1) };
+        let mut data: [Cell<N, T>; U] = cells.split_off(4).try_into().unwrap();
+        let mut head: [Cell<N, T>; 4] = cells.try_into().unwrap();
+
+        for cell in &mut head {
+            cell.set(T::ZERO);
+        }
+
+        for index in 0..U {
+            data[index].set(value[index]);
+        }
+
+        CellArray { head, cells: data }
+    }
+
+    /// Creates an empty `CellStack` which can hold up to `U` values.
+    pub fn cell_stack<const U: usize>(&self) -> CellStack<'_, N, U, T> {
+        CellStack {
+            array: self.cell_array([T::ZERO; U]),
+            len: self.cell(T::ZERO),
+        }
+    }
+
+    /// Creates an array of initialized cells guaranteed to be consecutive in memory, where the first
+    /// cell's location is a multiple of `alignment`.
+    pub fn aligned_array<const U: usize>(
+        &self,
+        alignment: usize,
+        value: [T; U],
+    ) -> [Cell<'_, N, T>; U] {
+        let mut cells = unsafe { self.aligned_array_uninit(alignment) };
+
+        for index in 0..U {
+            cells[index].set(value[index]);
+        }
+
+        cells
+    }
+
     /// Creates a new uninitialized cell.
     ///
     /// ## Safety
@@ -170,6 +346,18 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
         cell
     }
 
+    /// Creates a new cell containing the sum of the values of every cell in `cells`, which wraps
+    /// or saturates the same way adding to a single cell does. Every cell in `cells` is preserved.
+    pub fn sum_cells<'a>(&'a self, cells: &[Cell<'a, N, T>]) -> Cell<'a, N, T> {
+        let mut sum = self.cell(T::ZERO);
+
+        for cell in cells {
+            sum += cell;
+        }
+
+        sum
+    }
+
     /// Creates a new `CellString` with a specific value.
     pub fn str<'a, 'b>(&'a self, source: &'b str) -> CellString<'a, 'b, N, T> {
         CellString {
@@ -178,6 +366,21 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
         }
     }
 
+    /// Creates a new `CharWriter`, whose cell starts at zero.
+    pub fn char_writer(&self) -> CharWriter<'_, N, T> {
+        CharWriter {
+            cell: self.cell(T::ZERO),
+            value: 0,
+        }
+    }
+
+    /// Creates a new `RingBuffer` holding `U` cells, all of which start at zero.
+    pub fn ring_buffer<const U: usize>(&self) -> RingBuffer<'_, N, U, T> {
+        RingBuffer {
+            cells: self.array([T::ZERO; U]),
+        }
+    }
+
     /// Creates a new `CellString` with a specific value and writes it.
     pub fn write<'a>(&'a self, source: &str)
     where
@@ -189,6 +392,34 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
         self.str(source).write();
     }
 
+    /// Moves the pointer right from `from` until it reaches a cell containing zero, using `[>]`.
+    ///
+    /// Since the cell the pointer lands on is only known at runtime, the builder's pointer position
+    /// becomes unknown afterwards, and moving to any cell will panic until `.resync_pointer()` is
+    /// called.
+    pub fn scan_right_to_zero(&self, from: &Cell<N, T>) {
+        from.goto();
+        *self.source.borrow_mut() += "[>]";
+        *self.pointer.borrow_mut() = None;
+    }
+
+    /// Moves the pointer left from `from` until it reaches a cell containing zero, using `[<]`.
+    ///
+    /// Since the cell the pointer lands on is only known at runtime, the builder's pointer position
+    /// becomes unknown afterwards, and moving to any cell will panic until `.resync_pointer()` is
+    /// called.
+    pub fn scan_left_to_zero(&self, from: &Cell<N, T>) {
+        from.goto();
+        *self.source.borrow_mut() += "[<]";
+        *self.pointer.borrow_mut() = None;
+    }
+
+    /// Tells the builder that the pointer is currently at `known`. This does not generate any code,
+    /// so the generated program must actually leave the pointer at `known` for later code to work.
+    pub fn resync_pointer(&self, known: &Cell<N, T>) {
+        *self.pointer.borrow_mut() = Some(known.location);
+    }
+
     /// Creates a new cell containing the next byte of input, or `T::ZERO` if there is no input
     /// left.
     pub fn read(&self) -> Cell<N, T> {
@@ -204,6 +435,38 @@ impl<const N: usize, T: CellValue> Builder<N, T> {
         cell.read();
         cell
     }
+
+    /// Creates a new cell containing a base-10 number read from input. ASCII digits are read until
+    /// the first byte which isn't a digit, which is consumed, or until there is no input left.
+    pub fn read_decimal(&self) -> Cell<'_, N, T> {
+        let mut value = self.cell(T::ZERO);
+
+        let mut ten = self.cell(T::ZERO);
+        ten.add_constant(10);
+        let mut zero_digit = self.cell(T::ZERO);
+        zero_digit.add_constant(b'0' as isize);
+        let mut past_nine_digit = self.cell(T::ZERO);
+        past_nine_digit.add_constant(b'9' as isize + 1);
+
+        let mut char = self.read();
+        let mut is_digit = char.ge(&zero_digit).and(&char.lt(&past_nine_digit));
+
+        is_digit.while_nonzero_mut(|is_digit| {
+            value *= &ten;
+            char.add_constant(-(b'0' as isize));
+            value += &char;
+
+            char.zero();
+            char.read();
+
+            is_digit.zero();
+            char.ge(&zero_digit)
+                .and(&char.lt(&past_nine_digit))
+                .move_into(is_digit);
+        });
+
+        value
+    }
 }
 
 struct VerbatimDebug(String);
@@ -233,7 +496,7 @@ impl<const N: usize, T: CellValue> fmt::Debug for Builder<N, T> {
             .iter()
             .enumerate()
             .map(|(index, is_filled)| {
-                if index == pointer {
+                if Some(index) == pointer {
                     if *is_filled {
                         "1̲"
                     } else {
@@ -257,3 +520,186 @@ impl<const N: usize, T: CellValue> fmt::Debug for Builder<N, T> {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::Wrapping;
+
+    #[test]
+    fn scan_and_resync_emit_expected_source() {
+        let builder = Builder::<8, Wrapping<u8>>::new();
+        let start = builder.cell(Wrapping(1));
+        let mut end = builder.cell(Wrapping(0));
+
+        builder.scan_right_to_zero(&start);
+        builder.resync_pointer(&end);
+        end.inc();
+
+        assert_eq!(builder.source(), "[-]+>[-]<[>]+");
+    }
+
+    #[test]
+    #[should_panic(expected = "pointer position is unknown")]
+    fn goto_panics_after_scan_without_resync() {
+        let builder = Builder::<8, Wrapping<u8>>::new();
+        let start = builder.cell(Wrapping(1));
+        let mut end = builder.cell(Wrapping(0));
+
+        builder.scan_left_to_zero(&start);
+        end.inc();
+    }
+
+    #[test]
+    fn aligned_array_starts_at_multiple_of_alignment() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let _first = builder.cell(Wrapping(1));
+        let array = builder.aligned_array(4, [Wrapping(1), Wrapping(2), Wrapping(3), Wrapping(4)]);
+
+        assert_eq!(array[0].location() % 4, 0);
+        assert_ne!(array[0].location(), 0);
+
+        for (index, cell) in array.iter().enumerate() {
+            assert_eq!(cell.location(), array[0].location() + index);
+        }
+    }
+
+    #[test]
+    fn reset_builds_next_program_from_clean_state() {
+        let builder = Builder::<8, Wrapping<u8>>::new();
+        {
+            let a = builder.cell(Wrapping(3));
+            let b = builder.cell(Wrapping(4));
+            b.write();
+            a.write();
+        }
+        builder.reset();
+        assert_eq!(builder.source(), "");
+
+        let fresh = Builder::<8, Wrapping<u8>>::new();
+        for builder in [&builder, &fresh] {
+            let cell = builder.cell(Wrapping(5));
+            assert_eq!(cell.location(), 0);
+            cell.write();
+        }
+
+        assert_eq!(builder.source(), fresh.source());
+        assert_eq!(
+            builder.run_to_vec(std::iter::empty()).unwrap(),
+            [Wrapping(5)]
+        );
+    }
+
+    #[test]
+    fn sum_cells_adds_and_preserves_every_cell() {
+        let builder = Builder::<16, u8>::new();
+        let cells = [1, 2, 3, 4].map(|value| builder.cell(value));
+        builder.sum_cells(&cells).write();
+        cells.iter().for_each(|cell| cell.write());
+        assert_eq!(
+            builder.run_to_vec(std::iter::empty()).unwrap(),
+            [10, 1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn sum_cells_wraps_past_255() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let cells = [200, 100, 50].map(|value| builder.cell(Wrapping(value)));
+        builder.sum_cells(&cells).write();
+        builder.sum_cells(&[]).write();
+        assert_eq!(
+            builder.run_to_vec(std::iter::empty()).unwrap(),
+            [Wrapping(94), Wrapping(0)]
+        );
+    }
+
+    #[test]
+    fn run_to_vec_returns_output_of_decimal_reader() {
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        builder.read_decimal().write();
+
+        for (input, expected) in [("123\n", 123), ("42 7", 42), ("", 0)] {
+            let input = input.bytes().map(Wrapping);
+            assert_eq!(builder.run_to_vec(input).unwrap(), [Wrapping(expected)]);
+        }
+    }
+
+    #[test]
+    fn run_to_string_converts_each_value_to_a_character() {
+        let builder = Builder::<8, u8>::new();
+        builder.write("Hi!\n");
+        assert_eq!(builder.run_to_string(std::iter::empty()).unwrap(), "Hi!\n");
+    }
+
+    #[test]
+    fn run_optimized_matches_run_on_decimal_reader() {
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        builder.read_decimal().write();
+
+        let (program, optimized) = (
+            builder.compile().unwrap(),
+            builder.compile_optimized().unwrap(),
+        );
+        assert!(optimized.to_string().len() <= program.to_string().len());
+
+        for input in ["0", "7\n", "123 ", "255", "300"] {
+            let input = || input.bytes().map(Wrapping);
+            let plain = builder.run(input(), Vec::new()).unwrap();
+            let fast = builder.run_optimized(input(), Vec::new()).unwrap();
+            assert_eq!(plain.output(), fast.output());
+            assert_eq!(plain.memory(), fast.memory());
+        }
+    }
+
+    #[test]
+    fn source_is_a_snapshot_and_reset_allows_reuse() {
+        let builder = Builder::<8, u8>::new();
+        assert_eq!(builder.source(), "");
+
+        for char in *b"ab" {
+            {
+                let cell = builder.cell(char);
+                let before = builder.source();
+                cell.write();
+                assert!(!before.contains('.'));
+                assert!(builder.source().starts_with(&before));
+                assert!(builder.source().ends_with('.'));
+            }
+
+            assert_eq!(builder.run_to_vec(std::iter::empty()).unwrap(), [char]);
+            builder.reset();
+            assert_eq!(builder.source(), "");
+        }
+    }
+
+    #[test]
+    fn read_decimal_parses_digits_and_consumes_terminator() {
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        let value = builder.read_decimal();
+        let next = builder.read();
+
+        let runner = builder
+            .run("253\nx".bytes().map(Wrapping), Vec::new())
+            .unwrap();
+        assert_eq!(runner.cell_value(value.location()), Wrapping(253));
+        assert_eq!(runner.cell_value(next.location()), Wrapping(b'x'));
+    }
+
+    #[test]
+    fn clear_discards_the_previous_program() {
+        let builder = Builder::<8, u8>::new();
+        builder.cell(b'+').write();
+        let first = builder.source();
+        assert_eq!(builder.run_to_vec(std::iter::empty()).unwrap(), *b"+");
+
+        builder.clear();
+        assert_eq!(builder.source(), "");
+
+        builder.read().write();
+        let second = builder.source();
+        assert!(!second.contains(&first));
+        assert!(!second.contains('+'));
+        assert_eq!(builder.run_to_vec([7].into_iter()).unwrap(), [7]);
+    }
+}
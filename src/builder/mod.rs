@@ -1,6 +1,11 @@
 //! Defines an allocator which can build brainfuck programs.
 
+pub mod array;
 pub mod cell;
 pub mod core;
+pub mod op_log;
+pub mod programs;
+pub mod ring_buffer;
+pub mod stack;
 pub mod string;
 pub mod types;
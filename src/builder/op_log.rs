@@ -0,0 +1,94 @@
+//! Defines an optional log of the operations performed by a builder, which can be used to find
+//! which operations generate the most code.
+
+use super::{core::Builder, types::CellValue};
+
+/// A kind of operation which may be recorded in a builder's operation log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpKind {
+    /// Setting a cell to a constant value.
+    Set,
+    /// Adding a constant or another cell to a cell.
+    Add,
+    /// Subtracting a constant or another cell from a cell.
+    Sub,
+    /// Multiplying a cell by another cell.
+    Mul,
+    /// Dividing a cell by another cell.
+    Div,
+    /// Taking the remainder of dividing a cell by another cell.
+    Rem,
+    /// Copying a cell into other cells.
+    Copy,
+    /// Looping while a cell is nonzero, including the loop's body.
+    Loop,
+}
+
+/// A record of a single operation performed by a builder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpReport {
+    /// The kind of operation which was performed.
+    pub kind: OpKind,
+    /// The number of characters of source code the operation generated, including the code
+    /// generated by any nested operations.
+    pub source_length: usize,
+}
+
+/// Records an operation in a builder's log when dropped, if the log is enabled. Operations are
+/// recorded in the order they finish, so nested operations appear before the ones containing them.
+pub(super) struct OpGuard<'a, const N: usize, T: CellValue> {
+    builder: &'a Builder<N, T>,
+    kind: OpKind,
+    start: usize,
+}
+
+impl<'a, const N: usize, T: CellValue> OpGuard<'a, N, T> {
+    /// Starts recording an operation.
+    pub(super) fn new(builder: &'a Builder<N, T>, kind: OpKind) -> Self {
+        Self {
+            builder,
+            kind,
+            start: builder.source.borrow().len(),
+        }
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> Drop for OpGuard<'a, N, T> {
+    fn drop(&mut self) {
+        if let Some(log) = &mut *self.builder.op_log.borrow_mut() {
+            log.push(OpReport {
+                kind: self.kind,
+                source_length: self.builder.source.borrow().len() - self.start,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::Wrapping;
+
+    #[test]
+    fn division_generates_more_source_than_multiplication() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut product = builder.cell(Wrapping(6));
+        let mut quotient = builder.cell(Wrapping(42));
+        let rhs = builder.cell(Wrapping(7));
+
+        builder.enable_op_log();
+        product *= &rhs;
+        quotient /= &rhs;
+
+        let report = builder.op_report();
+        let length = |kind| {
+            report
+                .iter()
+                .find(|op| op.kind == kind)
+                .expect("operation should be logged")
+                .source_length
+        };
+
+        assert!(length(OpKind::Div) > 2 * length(OpKind::Mul));
+    }
+}
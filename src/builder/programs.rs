@@ -0,0 +1,203 @@
+//! Provides complete programs built out of the builder's primitives.
+
+use super::{core::Builder, types::CellValue};
+use crate::program::Program;
+use std::num::Wrapping;
+
+impl<const N: usize, T: CellValue> Builder<N, T> {
+    /// Adds code to this builder which reads input until a zero byte or the end of input, then
+    /// writes everything that was read in reverse order, and compiles everything this builder has
+    /// generated so far into a program. At most `max_len` bytes are read; anything after that is
+    /// left unread. The limit is needed because the input is stored in cells allocated from this
+    /// builder's `N` cells.
+    ///
+    /// The input is stored in `max_len + 2` consecutive cells, the outer two of which are kept at
+    /// zero so that the stored bytes can be walked with `[>]` and `[.<]` loops. Because every walk
+    /// ends on a zero cell at a known position, the builder's pointer is resynced afterwards.
+    pub fn reverse_input_program(&self, max_len: usize) -> Program {
+        let mut cells = unsafe { self.consecutive_uninit(max_len + 2, 1) };
+
+        for cell in &mut cells {
+            cell.zero();
+        }
+
+        if max_len != 0 {
+            cells[1].read();
+        }
+
+        for index in 2..=max_len {
+            let [mut previous_was_read] = cells[index - 1].copy();
+            let cell = &mut cells[index];
+
+            previous_was_read.while_nonzero_mut(|previous_was_read| {
+                cell.read();
+                previous_was_read.zero();
+            });
+        }
+
+        // This stops on the zero cell just past the last byte read. Writing while walking back has
+        // no primitive of its own, so that walk is added directly and ends on the leading zero.
+        self.scan_right_to_zero(&cells[1]);
+        *self.source.borrow_mut() += "<[.<]";
+        self.resync_pointer(&cells[0]);
+
+        drop(cells);
+
+        self.compile()
+            .expect("builders should always generate matching brackets")
+    }
+}
+
+impl<const N: usize> Builder<N, Wrapping<u8>> {
+    /// Adds code to this builder which writes an `n` by `n` multiplication table. Each row is ended
+    /// by a newline, and the products in a row are separated by spaces and right-aligned to the
+    /// width of the largest product.
+    ///
+    /// The rows and columns are counted by loops in the generated program, so its length barely
+    /// depends on `n`.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `n` is larger than 15, since `n * n` would not fit in a cell.
+    pub fn print_times_table(&self, n: u8) {
+        let largest = n
+            .checked_mul(n)
+            .expect("times table should fit in a single cell");
+
+        // Products smaller than each of these are padded with one more space.
+        let thresholds: Vec<u8> = [10, 100].into_iter().filter(|&x| x <= largest).collect();
+
+        let space = self.cell(Wrapping(b' '));
+        let newline = self.cell(Wrapping(b'\n'));
+        let mut row = self.cell(Wrapping(0));
+        let mut rows_left = self.cell(Wrapping(n));
+
+        rows_left.while_nonzero_mut(|rows_left| {
+            rows_left.dec();
+            row.inc();
+
+            let mut product = self.cell(Wrapping(0));
+            let mut columns_left = self.cell(Wrapping(n));
+
+            columns_left.while_nonzero_mut(|columns_left| {
+                columns_left.dec();
+                product += &row;
+
+                for &threshold in &thresholds {
+                    let threshold = self.cell(Wrapping(threshold));
+                    product.lt(&threshold).if_nonzero_and_zero(|| space.write());
+                }
+
+                product.write_decimal();
+                columns_left.if_nonzero(|| space.write());
+            });
+
+            newline.write();
+        });
+    }
+
+    /// Adds code to this builder which reads input until a zero byte or the end of input, writing
+    /// each byte back with letters rotated `n` places through the alphabet. Uppercase and lowercase
+    /// letters wrap around within their own case, and all other bytes are written unchanged, so
+    /// `n = 13` gives ROT13.
+    pub fn rot_n_stream(&self, n: u8) {
+        let n = n % 26;
+        let mut input = self.read();
+
+        input.while_nonzero_mut(|input| {
+            for first in *b"Aa" {
+                let mut is_letter = input.in_range(Wrapping(first), Wrapping(first + 25));
+
+                is_letter.if_nonzero_and_zero(|| {
+                    *input += Wrapping(n);
+
+                    // Rotated letters never reach the other case's range, so they are only
+                    // shifted once.
+                    let mut past_end =
+                        input.in_range(Wrapping(first + 26), Wrapping(first + 25 + n));
+                    past_end.if_nonzero_and_zero(|| *input -= Wrapping(26));
+                });
+            }
+
+            input.write();
+            input.zero();
+            input.read();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_input_program_reverses_input() {
+        let program = Builder::<16, u8>::new().reverse_input_program(8);
+        let runner = program.run::<16, _, _, u8>("abc".bytes(), Vec::new());
+        assert_eq!(runner.output(), b"cba");
+    }
+
+    #[test]
+    fn reverse_input_program_stops_at_max_len() {
+        let program = Builder::<16, u8>::new().reverse_input_program(2);
+        let runner = program.run::<16, _, _, u8>("abc".bytes(), Vec::new());
+        assert_eq!(runner.output(), b"ba");
+    }
+
+    #[test]
+    fn print_times_table_prints_3_by_3() {
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        builder.print_times_table(3);
+        let output = builder.run_to_string(std::iter::empty()).unwrap();
+        assert_eq!(output, "1 2 3\n2 4 6\n3 6 9\n");
+    }
+
+    #[test]
+    fn print_times_table_aligns_wider_products() {
+        let builder = Builder::<64, Wrapping<u8>>::new();
+        builder.print_times_table(4);
+        let output = builder.run_to_string(std::iter::empty()).unwrap();
+        assert_eq!(
+            output,
+            " 1  2  3  4\n 2  4  6  8\n 3  6  9 12\n 4  8 12 16\n"
+        );
+    }
+
+    #[test]
+    fn rot_n_stream_rot13_rotates_letters_only() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        builder.rot_n_stream(13);
+        let program = builder.compile().unwrap();
+
+        let rotate = |text: &str| {
+            let runner = program.run::<32, _, _, _>(text.bytes().map(Wrapping), Vec::new());
+            runner
+                .output()
+                .iter()
+                .map(|value| value.0 as char)
+                .collect::<String>()
+        };
+
+        assert_eq!(rotate("Hello, World!"), "Uryyb, Jbeyq!");
+        assert_eq!(rotate("Uryyb, Jbeyq!"), "Hello, World!");
+        assert_eq!(rotate("azAZ@[`{ 09"), "nmNM@[`{ 09");
+    }
+
+    #[test]
+    fn reverse_input_program_keeps_earlier_code_and_cells() {
+        let builder = Builder::<16, u8>::new();
+        builder.write("> ");
+        let kept = builder.cell(b'!');
+
+        let program = builder.reverse_input_program(4);
+        assert!(builder.source().contains("[>]<[.<]"));
+        let runner = program.run::<16, _, _, u8>("abc".bytes(), Vec::new());
+        assert_eq!(runner.output(), b"> cba");
+        assert_eq!(runner.cell_value(kept.location()), b'!');
+
+        // The pointer is tracked again afterwards, so more code can be added to the builder.
+        kept.write();
+        let runner = builder.run("abc".bytes(), Vec::new()).unwrap();
+        assert_eq!(runner.output(), b"> cba!");
+    }
+}
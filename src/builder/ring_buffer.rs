@@ -0,0 +1,59 @@
+//! Defines a fixed-size delay line built out of consecutive cells.
+
+use super::{cell::Cell, types::CellValue};
+
+/// A buffer of `U` cells which emits the oldest value it holds whenever a new value is pushed.
+/// Until `U` values have been pushed, the emitted values are zero.
+#[derive(Debug)]
+pub struct RingBuffer<'a, const N: usize, const U: usize, T: CellValue> {
+    pub(super) cells: [Cell<'a, N, T>; U],
+}
+
+impl<'a, const N: usize, const U: usize, T: CellValue> RingBuffer<'a, N, U, T> {
+    /// Writes the oldest value in this buffer, shifts every other value one place towards the
+    /// front, and stores a copy of `input` at the back. `input` is preserved.
+    pub fn push_and_emit(&mut self, input: &Cell<'a, N, T>) {
+        let Some((oldest, _)) = self.cells.split_first_mut() else {
+            input.write();
+            return;
+        };
+
+        oldest.write();
+
+        for index in 1..U {
+            let (front, back) = self.cells.split_at_mut(index);
+            back[0].move_into_and_zero(&mut front[index - 1]);
+        }
+
+        self.cells[U - 1] += input;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::core::Builder;
+    use std::num::Wrapping;
+
+    #[test]
+    fn push_and_emit_delays_values() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut buffer = builder.ring_buffer::<3>();
+
+        for value in 1..=5 {
+            buffer.push_and_emit(&builder.cell(Wrapping(value)));
+        }
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [0, 0, 0, 1, 2].map(Wrapping));
+    }
+
+    #[test]
+    fn empty_buffer_emits_input_immediately() {
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut buffer = builder.ring_buffer::<0>();
+        buffer.push_and_emit(&builder.cell(Wrapping(7)));
+
+        let output = builder.run_to_vec(std::iter::empty()).unwrap();
+        assert_eq!(output, [Wrapping(7)]);
+    }
+}
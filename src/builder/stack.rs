@@ -0,0 +1,90 @@
+//! Defines a stack of cells whose top is only known at runtime.
+
+use super::{array::CellArray, cell::Cell, types::CellValue};
+
+/// A stack which can hold up to `U` values, stored in a `CellArray` alongside a cell holding the
+/// number of values currently on the stack. Pushing and popping walk to the top of the stack at
+/// runtime, so the generated code has the same length no matter how many values are stored.
+///
+/// The capacity is not checked at runtime. Pushing onto a full stack writes past the end of the
+/// array and corrupts unrelated cells, and popping from an empty stack walks far past the end of
+/// the array, which usually corrupts cells or moves the pointer outside of memory.
+#[derive(Debug)]
+pub struct CellStack<'a, const N: usize, const U: usize, T: CellValue> {
+    pub(super) array: CellArray<'a, N, U, T>,
+    pub(super) len: Cell<'a, N, T>,
+}
+
+impl<'a, const N: usize, const U: usize, T: CellValue> CellStack<'a, N, U, T> {
+    /// Gets the maximum number of values this stack can hold.
+    pub fn capacity(&self) -> usize {
+        U
+    }
+
+    /// Gets the cell holding the number of values currently on this stack.
+    pub fn size(&self) -> &Cell<'a, N, T> {
+        &self.len
+    }
+
+    /// Pushes a copy of `value` onto this stack. `value` is preserved.
+    pub fn push(&mut self, value: &Cell<'a, N, T>) {
+        self.array.set_dynamic(&self.len, value);
+        self.len.inc();
+    }
+
+    /// Removes the top value of this stack and returns it in a new cell.
+    pub fn pop(&mut self) -> Cell<'a, N, T> {
+        self.len.dec();
+        self.array.take_dynamic(&self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::core::Builder;
+    use std::num::Wrapping;
+
+    #[test]
+    fn pops_values_in_reverse_order_of_pushes() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut stack = builder.cell_stack::<4>();
+        assert_eq!(stack.capacity(), 4);
+
+        // Values come from input so their order is only known at runtime.
+        for _ in 0..4 {
+            stack.push(&builder.read());
+        }
+
+        stack.size().write();
+        stack.pop().write();
+        stack.pop().write();
+        stack.push(&builder.read());
+
+        for _ in 0..3 {
+            stack.pop().write();
+        }
+
+        stack.size().write();
+
+        let input = [5, 6, 7, 8, 9].map(Wrapping).into_iter();
+        let output = builder.run_to_vec(input).unwrap();
+        assert_eq!(output, [4, 8, 7, 9, 6, 5, 0].map(Wrapping));
+    }
+
+    #[test]
+    fn push_three_then_pop_three() {
+        let builder = Builder::<32, Wrapping<u8>>::new();
+        let mut stack = builder.cell_stack::<3>();
+
+        for value in [1, 2, 3] {
+            stack.push(&builder.cell(Wrapping(value)));
+        }
+
+        let popped = [(); 3].map(|_| stack.pop());
+        popped.iter().for_each(|cell| cell.write());
+        stack.size().write();
+
+        let runner = builder.run(std::iter::empty(), Vec::new()).unwrap();
+        assert_eq!(runner.output(), &[3, 2, 1, 0].map(Wrapping));
+    }
+}
@@ -48,3 +48,52 @@ impl<'a, 'b, const N: usize, T: CellValue> CellString<'a, 'b, N, T> {
         }
     }
 }
+
+/// A cell used to write individual characters, which remembers the character it last wrote. Each
+/// write only adjusts the cell by the difference between the new character and the previous one,
+/// so writing similar characters generates much less code than setting the cell every time.
+#[must_use]
+pub struct CharWriter<'a, const N: usize, T: CellValue> {
+    pub(super) cell: Cell<'a, N, T>,
+    pub(super) value: u8,
+}
+
+impl<'a, const N: usize, T: CellValue> fmt::Debug for CharWriter<'a, N, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CharWriter")
+            .field("cell", &self.cell)
+            .field("value", &self.value)
+            .finish()
+    }
+}
+
+impl<'a, const N: usize, T: CellValue> CharWriter<'a, N, T> {
+    /// Writes a single character, adding or subtracting the difference between it and the
+    /// previously written character.
+    pub fn write_char(&mut self, char: u8) {
+        self.cell.add_constant(char as isize - self.value as isize);
+        self.cell.write();
+        self.value = char;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::core::Builder;
+
+    #[test]
+    fn write_char_only_adds_the_difference() {
+        let builder = Builder::<8, u8>::new();
+        let mut writer = builder.char_writer();
+        writer.write_char(b'A');
+        let first = builder.source();
+
+        writer.write_char(b'B');
+        writer.write_char(b'A');
+        writer.write_char(b'A');
+        assert_eq!(builder.source(), first + "+.-..");
+
+        drop(writer);
+        assert_eq!(builder.run_to_vec(std::iter::empty()).unwrap(), *b"ABAA");
+    }
+}
@@ -1,55 +1,217 @@
 //! Defines traits that values can implement to allow storing them in brainfuck cells.
 
-use std::num::Wrapping;
+use std::num::{Saturating, Wrapping};
 
 /// A value that may be stored inside a brainfuck memory cell.
 pub trait CellValue: PartialEq + Copy {
     /// The zero value of this type.
     const ZERO: Self;
 
-    /// Increments this value by one. Wrapping is undefined behavior unless in a `Wrapping<T>`.
+    /// Whether this type wraps around when incremented past its maximum value or decremented past
+    /// its minimum value. Only values which wrap can have additions in opposite directions combined
+    /// or have `[-]` replaced with setting them to zero, since a saturated value forgets how far
+    /// past its limit it was pushed. Overflowing a plain integer is undefined behavior, so plain
+    /// integers count as wrapping.
+    const WRAPS: bool;
+
+    /// Increments this value by one. Wrapping is undefined behavior unless in a `Wrapping<T>`, and
+    /// a `Saturating<T>` stays at its maximum value instead.
     fn inc(self) -> Self;
 
-    /// Decrements this value by one. Wrapping is undefined behavior unless in a `Wrapping<T>`.
+    /// Decrements this value by one. Wrapping is undefined behavior unless in a `Wrapping<T>`, and
+    /// a `Saturating<T>` stays at its minimum value instead.
     fn dec(self) -> Self;
 
+    /// Adds a signed amount to this value, as if it were incremented or decremented `amount` times.
+    /// Wrapping is undefined behavior unless in a `Wrapping<T>`.
+    fn offset(self, amount: isize) -> Self {
+        let mut value = self;
+
+        if amount < 0 {
+            for _ in amount..0 {
+                value = value.dec();
+            }
+        } else {
+            for _ in 0..amount {
+                value = value.inc();
+            }
+        }
+
+        value
+    }
+
+    /// Adds `factor` to this value `count` times, where `count` is the number of times `count` would
+    /// need to be decremented to reach zero. This is how a loop like `[->+++<]` changes its target.
+    fn add_product(self, count: Self, factor: isize) -> Self {
+        let mut value = self;
+        let mut count = count;
+
+        while count != Self::ZERO {
+            value = value.offset(factor);
+            count = count.dec();
+        }
+
+        value
+    }
+
+    /// Gets the value this becomes after running a loop which adds `step` to it until it is zero,
+    /// such as `[-]` when `step` is -1 or `[+]` when `step` is 1, or `None` if the loop never
+    /// finishes. Values which wrap always become zero. Other values have `step` added one at a
+    /// time, just like the loop does, stopping early if that stops changing the value.
+    ///
+    /// A value which saturates before reaching zero sticks at its limit, so the loop never finishes.
+    /// This happens when running `[-]` on a negative `Saturating` integer or `[+]` on a positive
+    /// one.
+    fn cleared(self, step: isize) -> Option<Self> {
+        if Self::WRAPS {
+            return Some(Self::ZERO);
+        }
+
+        self.steps_to_zero(step).map(|_| Self::ZERO)
+    }
+
+    /// Counts how many times a loop which adds `step` to this value runs before the value is zero,
+    /// where `step` is -1 for `[-]` or 1 for `[+]`. Values which wrap count the distance to zero
+    /// around the end of their range, so `[-]` runs 251 times on an 8-bit -5. Returns `None` if the
+    /// loop never finishes because the value saturates before reaching zero.
+    fn steps_to_zero(self, step: isize) -> Option<u128> {
+        let mut value = self;
+        let mut steps = 0;
+
+        while value != Self::ZERO {
+            let next = value.offset(step);
+
+            if next == value {
+                return None;
+            }
+
+            value = next;
+            steps += 1;
+        }
+
+        Some(steps)
+    }
+
+    /// Converts this value into an isize, or returns `None` if it is not possible to fit in an
+    /// `isize`.
+    fn try_into_isize(self) -> Option<isize>;
+
     /// Converts this value into an isize.
     ///
     /// ## Panics
     ///
     /// Panics if the value of this cell is not possible to fit in an `isize`.
-    fn into_isize(self) -> isize;
+    fn into_isize(self) -> isize {
+        self.try_into_isize()
+            .expect("cell value should fit in an isize")
+    }
+
+    /// Splits this value into a high part and a low part such that the value equals
+    /// `high * 2^24 + low`, where both parts have the same sign as the value and `low` always fits
+    /// in an `isize`. This lets constants which are too large for an `isize` be built up in pieces.
+    /// Values which already fit in an `isize` may return a high part of zero.
+    fn split_isize(self) -> (Self, isize) {
+        (Self::ZERO, self.into_isize())
+    }
 }
 
 /// A value that may be debugged in a brainfuck `Runner`'s input or output.
 pub trait DebuggableCellValue: CellValue {
     /// Converts this cell value into a valid Unicode character.
     fn into_char(self) -> char;
+
+    /// Reinterprets the bits of this cell value as a two's complement signed integer, so an
+    /// unsigned `200u8` becomes -56. Signed values are returned as-is.
+    fn into_signed(self) -> i128;
 }
 
+/// A value with a sign that may be stored inside a brainfuck memory cell.
+pub trait SignedCellValue: CellValue {}
+
 macro_rules! cell_value_impl_u {
     ($($x:ty)+) => {
         $(
             impl CellValue for $x {
                 const ZERO: Self = 0;
+                const WRAPS: bool = true;
                 fn inc(self) -> Self { self + 1 }
                 fn dec(self) -> Self { self - 1 }
-                fn into_isize(self) -> isize { self.try_into().unwrap() }
+                fn offset(self, amount: isize) -> Self {
+                    let magnitude = <$x>::try_from(amount.unsigned_abs()).unwrap();
+                    if amount < 0 { self - magnitude } else { self + magnitude }
+                }
+                fn add_product(self, count: Self, factor: isize) -> Self {
+                    let magnitude = <$x>::try_from(factor.unsigned_abs()).unwrap() * count;
+                    if factor < 0 { self - magnitude } else { self + magnitude }
+                }
+                fn steps_to_zero(self, step: isize) -> Option<u128> {
+                    Some(if step < 0 { self } else { self.wrapping_neg() } as u128)
+                }
+                fn try_into_isize(self) -> Option<isize> { self.try_into().ok() }
+                fn split_isize(self) -> (Self, isize) {
+                    ((self as u128 / 0x100_0000) as $x, (self as u128 % 0x100_0000) as isize)
+                }
             }
 
             impl DebuggableCellValue for $x {
                 fn into_char(self) -> char { u32::try_from(self).unwrap().try_into().unwrap() }
+                fn into_signed(self) -> i128 {
+                    let shift = 128 - <$x>::BITS;
+                    ((self as i128) << shift) >> shift
+                }
             }
 
             impl CellValue for Wrapping<$x> {
                 const ZERO: Self = Wrapping(0);
+                const WRAPS: bool = true;
                 fn inc(self) -> Self { self + Wrapping(1) }
                 fn dec(self) -> Self { self - Wrapping(1) }
-                fn into_isize(self) -> isize { self.0.try_into().unwrap() }
+                fn offset(self, amount: isize) -> Self { self + Wrapping(amount as $x) }
+                fn add_product(self, count: Self, factor: isize) -> Self {
+                    self + Wrapping(factor as $x) * count
+                }
+                fn steps_to_zero(self, step: isize) -> Option<u128> {
+                    Some(if step < 0 { self.0 } else { self.0.wrapping_neg() } as u128)
+                }
+                fn try_into_isize(self) -> Option<isize> { self.0.try_into().ok() }
+                fn split_isize(self) -> (Self, isize) {
+                    let value = self.0 as u128;
+                    (Wrapping((value / 0x100_0000) as $x), (value % 0x100_0000) as isize)
+                }
             }
 
             impl DebuggableCellValue for Wrapping<$x> {
                 fn into_char(self) -> char { u32::try_from(self.0).unwrap().try_into().unwrap() }
+                fn into_signed(self) -> i128 { self.0.into_signed() }
+            }
+
+            impl CellValue for Saturating<$x> {
+                const ZERO: Self = Saturating(0);
+                const WRAPS: bool = false;
+                fn inc(self) -> Self { self + Saturating(1) }
+                fn dec(self) -> Self { self - Saturating(1) }
+                fn offset(self, amount: isize) -> Self {
+                    let magnitude = <$x>::try_from(amount.unsigned_abs()).unwrap_or(<$x>::MAX);
+                    if amount < 0 { self - Saturating(magnitude) } else { self + Saturating(magnitude) }
+                }
+                fn add_product(self, count: Self, factor: isize) -> Self {
+                    let magnitude = <$x>::try_from(factor.unsigned_abs()).unwrap_or(<$x>::MAX);
+                    let product = Saturating(magnitude) * count;
+                    if factor < 0 { self - product } else { self + product }
+                }
+                fn steps_to_zero(self, step: isize) -> Option<u128> {
+                    (step < 0 || self.0 == 0).then_some(self.0 as u128)
+                }
+                fn try_into_isize(self) -> Option<isize> { self.0.try_into().ok() }
+                fn split_isize(self) -> (Self, isize) {
+                    let value = self.0 as u128;
+                    (Saturating((value / 0x100_0000) as $x), (value % 0x100_0000) as isize)
+                }
+            }
+
+            impl DebuggableCellValue for Saturating<$x> {
+                fn into_char(self) -> char { u32::try_from(self.0).unwrap().try_into().unwrap() }
+                fn into_signed(self) -> i128 { self.0.into_signed() }
             }
         )+
     };
@@ -60,14 +222,60 @@ macro_rules! cell_value_impl_i {
         $(
             impl CellValue for Wrapping<$x> {
                 const ZERO: Self = Wrapping(0);
+                const WRAPS: bool = true;
                 fn inc(self) -> Self { self + Wrapping(1) }
                 fn dec(self) -> Self { self - Wrapping(1) }
-                fn into_isize(self) -> isize { self.0.try_into().unwrap() }
+                fn offset(self, amount: isize) -> Self { self + Wrapping(amount as $x) }
+                fn add_product(self, count: Self, factor: isize) -> Self {
+                    self + Wrapping(factor as $x) * count
+                }
+                fn steps_to_zero(self, step: isize) -> Option<u128> {
+                    let value = if step < 0 { self.0 } else { self.0.wrapping_neg() };
+                    Some(value.cast_unsigned() as u128)
+                }
+                fn try_into_isize(self) -> Option<isize> { self.0.try_into().ok() }
+                fn split_isize(self) -> (Self, isize) {
+                    let value = self.0 as i128;
+                    (Wrapping((value / 0x100_0000) as $x), (value % 0x100_0000) as isize)
+                }
             }
 
             impl DebuggableCellValue for Wrapping<$x> {
                 fn into_char(self) -> char { u32::try_from(self.0).unwrap().try_into().unwrap() }
+                fn into_signed(self) -> i128 { self.0 as i128 }
+            }
+
+            impl CellValue for Saturating<$x> {
+                const ZERO: Self = Saturating(0);
+                const WRAPS: bool = false;
+                fn inc(self) -> Self { self + Saturating(1) }
+                fn dec(self) -> Self { self - Saturating(1) }
+                fn offset(self, amount: isize) -> Self {
+                    let value = (self.0 as i128).saturating_add(amount as i128);
+                    Saturating(value.clamp(<$x>::MIN as i128, <$x>::MAX as i128) as $x)
+                }
+                fn add_product(self, count: Self, factor: isize) -> Self {
+                    let product = (factor as i128).saturating_mul(count.0 as i128);
+                    let value = (self.0 as i128).saturating_add(product);
+                    Saturating(value.clamp(<$x>::MIN as i128, <$x>::MAX as i128) as $x)
+                }
+                fn steps_to_zero(self, step: isize) -> Option<u128> {
+                    let towards_zero = self.0 == 0 || (self.0 < 0) == (step > 0);
+                    towards_zero.then_some(self.0.unsigned_abs() as u128)
+                }
+                fn try_into_isize(self) -> Option<isize> { self.0.try_into().ok() }
+                fn split_isize(self) -> (Self, isize) {
+                    let value = self.0 as i128;
+                    (Saturating((value / 0x100_0000) as $x), (value % 0x100_0000) as isize)
+                }
             }
+
+            impl DebuggableCellValue for Saturating<$x> {
+                fn into_char(self) -> char { u32::try_from(self.0).unwrap().try_into().unwrap() }
+                fn into_signed(self) -> i128 { self.0 as i128 }
+            }
+
+            impl SignedCellValue for Wrapping<$x> {}
         )+
     };
 }
@@ -78,3 +286,59 @@ cell_value_impl_u! { u8 u16 u32 u64 u128 }
 // many of the existing algorithms. Only `Wrapping` variants of them are implemented.
 
 cell_value_impl_i! { i8 i16 i32 i64 i128 }
+
+/// A `bool` acts like a cell which can only hold zero (`false`) and one (`true`). Incrementing
+/// always produces `true` and decrementing always produces `false`, so incrementing `true` leaves it
+/// as `true` and decrementing `false` leaves it as `false`, much like a `Saturating` integer with a
+/// maximum of one.
+impl CellValue for bool {
+    const ZERO: Self = false;
+    const WRAPS: bool = false;
+
+    fn inc(self) -> Self {
+        true
+    }
+
+    fn dec(self) -> Self {
+        false
+    }
+
+    fn offset(self, amount: isize) -> Self {
+        if amount == 0 {
+            self
+        } else {
+            amount > 0
+        }
+    }
+
+    fn try_into_isize(self) -> Option<isize> {
+        Some(self as isize)
+    }
+}
+
+impl DebuggableCellValue for bool {
+    fn into_char(self) -> char {
+        char::from(self as u8)
+    }
+
+    fn into_signed(self) -> i128 {
+        self as i128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cleared_stops_when_a_value_saturates() {
+        assert_eq!(Saturating(5i8).cleared(-1), Some(Saturating(0)));
+        assert_eq!(Saturating(-5i8).cleared(1), Some(Saturating(0)));
+        assert_eq!(Saturating(-5i8).cleared(-1), None);
+        assert_eq!(Saturating(5u8).cleared(1), None);
+        assert_eq!(Saturating(0u8).cleared(1), Some(Saturating(0)));
+        assert_eq!(Wrapping(-5i8).cleared(-1), Some(Wrapping(0)));
+        assert_eq!(true.cleared(-1), Some(false));
+        assert_eq!(true.cleared(1), None);
+    }
+}
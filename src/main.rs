@@ -3,7 +3,7 @@
 #![feature(array_windows)]
 #![deny(unsafe_op_in_unsafe_fn, missing_docs, missing_debug_implementations)]
 
-use crate::builder::core::Builder;
+use crate::{builder::core::Builder, program::ParseError};
 use std::{
     io::{stdin, stdout, Read},
     num::Wrapping,
@@ -13,20 +13,10 @@ pub mod builder;
 pub mod program;
 pub mod runner;
 
-fn main() -> Result<(), &'static str> {
+fn main() -> Result<(), ParseError> {
     let builder = Builder::<65536, Wrapping<u8>>::new();
 
-    let mut value = builder.cell(Wrapping(0));
-
-    let mut input = builder.read();
-    input.while_nonzero_mut(|input| {
-        value *= Wrapping(10);
-        *input -= Wrapping(48);
-        value += &*input;
-        input.zero();
-        input.read();
-    });
-
+    let value = builder.read_decimal();
     value.write();
 
     eprintln!("{builder:?}");
@@ -2,85 +2,845 @@
 
 use crate::{
     builder::types::CellValue,
-    runner::{output::RunnerOutput, Runner},
+    runner::{
+        config::RunConfig, dynamic::DynRunner, error::RunError, input::RunnerInput, journal::Step,
+        output::RunnerOutput, Runner,
+    },
 };
+use std::{collections::HashMap, error::Error, fmt, num::Wrapping};
 
-#[derive(Debug)]
+/// The number of cells in the memory tape used by `run_bytes`, which matches the size used by most
+/// brainfuck implementations.
+const RUN_BYTES_TAPE_SIZE: usize = 30000;
+
+/// The greatest number of macros which may be expanded inside each other by `expand_macros`.
+const MAX_MACRO_DEPTH: usize = 64;
+
+#[derive(Clone, Debug)]
 enum Instruction {
-    Inc,
-    Dec,
-    Shl,
-    Shr,
+    /// Adds a value to the current cell. Runs of `+` or of `-` are folded into one of these.
+    Add(isize),
+    /// Moves the pointer by some offset. Runs of `<` or of `>` are folded into one of these.
+    Move(isize),
     Read,
     Write,
-    Repeat(Vec<Instruction>),
+    /// Runs a list of instructions while the current cell is nonzero. Loops compiled from source
+    /// keep the byte offset of their `[`, which `Program::run_profiled_on` uses to identify them.
+    /// The offset is ignored when comparing instructions, so programs compiled from differently
+    /// formatted source are still equal.
+    Repeat(Vec<Instruction>, Option<usize>),
+    /// Runs `[-]` or `[+]` on the current cell, which `Program::new` compiles into one of these
+    /// along with the amount the loop adds each time around, either -1 or 1. This sets the cell to
+    /// zero in constant time if its type wraps.
+    Clear(isize),
+    /// Adds the current cell's value, multiplied by a factor, to the cells at each offset, then
+    /// clears the current cell. Created by `Program::optimize` from loops like `[->++>+<<]`.
+    AddMul(Vec<(isize, isize)>),
+}
+
+impl PartialEq for Instruction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Instruction::Add(a), Instruction::Add(b))
+            | (Instruction::Move(a), Instruction::Move(b))
+            | (Instruction::Clear(a), Instruction::Clear(b)) => a == b,
+            (Instruction::Read, Instruction::Read) | (Instruction::Write, Instruction::Write) => {
+                true
+            }
+            (Instruction::Repeat(a, _), Instruction::Repeat(b, _)) => a == b,
+            (Instruction::AddMul(a), Instruction::AddMul(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Instruction {}
+
+/// Pushes an instruction which adds `amount` to the current cell, folding it into the previous
+/// instruction if that one also adds to the current cell.
+fn push_add(list: &mut Vec<Instruction>, amount: isize) {
+    if let Some(Instruction::Add(value)) = list.last_mut() {
+        *value += amount;
+
+        if *value == 0 {
+            list.pop();
+        }
+    } else {
+        list.push(Instruction::Add(amount));
+    }
+}
+
+/// Pushes an `Add` or `Move` instruction, folding it into the previous instruction only if that one
+/// is the same kind and goes in the same direction. Unlike `push_add` and `push_move`, this never
+/// changes the result for cells which saturate, since a saturated cell can't be pushed any further,
+/// and keeps one step for each character when counting towards `RunConfig::max_steps`.
+fn push_same_direction(list: &mut Vec<Instruction>, instruction: Instruction) {
+    match (list.last_mut(), &instruction) {
+        (Some(Instruction::Add(value)), Instruction::Add(amount))
+        | (Some(Instruction::Move(value)), Instruction::Move(amount))
+            if value.signum() == amount.signum() =>
+        {
+            *value += amount
+        }
+        _ => list.push(instruction),
+    }
+}
+
+/// Pushes an instruction which moves the pointer by `offset`, folding it into the previous
+/// instruction if that one also moves the pointer.
+fn push_move(list: &mut Vec<Instruction>, offset: isize) {
+    if let Some(Instruction::Move(value)) = list.last_mut() {
+        *value += offset;
+
+        if *value == 0 {
+            list.pop();
+        }
+    } else {
+        list.push(Instruction::Move(offset));
+    }
+}
+
+/// Gets an expression indexing the cell `offset` cells away from the pointer `p` in a tape called
+/// `mem`, which is valid in both C and Rust source.
+fn tape_cell(offset: isize) -> String {
+    match offset {
+        0 => "mem[p]".to_owned(),
+        ..0 => format!("mem[p - {}]", offset.unsigned_abs()),
+        _ => format!("mem[p + {offset}]"),
+    }
 }
 
-#[derive(Debug)]
+/// Finds where the pointer ends up after running a list of instructions and the rightmost cell it
+/// could visit, both relative to where it started. Returns `None` if these depend on the program's
+/// memory, which happens when a loop changes the position of the pointer.
+fn extent(list: &[Instruction]) -> Option<(isize, isize)> {
+    let mut pointer = 0;
+    let mut high_water_mark = 0;
+
+    for instruction in list {
+        match instruction {
+            Instruction::Move(offset) => {
+                pointer += offset;
+                high_water_mark = high_water_mark.max(pointer);
+            }
+            Instruction::Repeat(list, _) => {
+                let (offset, inner_high_water_mark) = extent(list)?;
+
+                if offset != 0 {
+                    return None;
+                }
+
+                high_water_mark = high_water_mark.max(pointer + inner_high_water_mark);
+            }
+            Instruction::AddMul(offsets) => {
+                for (offset, _) in offsets {
+                    high_water_mark = high_water_mark.max(pointer + offset);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some((pointer, high_water_mark))
+}
+
+/// The reason a brainfuck program could not be compiled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A `[` was never closed.
+    UnmatchedOpeningBracket,
+
+    /// A `]` had no `[` to close.
+    UnmatchedClosingBracket,
+
+    /// A `[` was nested inside more loops than allowed by `Program::new_with_max_depth`.
+    NestedTooDeeply,
+}
+
+/// An error which stopped a brainfuck program from being compiled, along with the position of the
+/// bracket which caused it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The reason the program could not be compiled.
+    pub kind: ParseErrorKind,
+
+    /// The byte offset of the offending bracket in the source.
+    pub offset: usize,
+
+    /// The line of the offending bracket, starting from 1.
+    pub line: usize,
+
+    /// The column of the offending bracket in characters, starting from 1.
+    pub column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self.kind {
+            ParseErrorKind::UnmatchedOpeningBracket => "unmatched opening bracket",
+            ParseErrorKind::UnmatchedClosingBracket => "unmatched closing bracket",
+            ParseErrorKind::NestedTooDeeply => "loop nested too deeply",
+        };
+
+        write!(f, "{message} at line {}, column {}", self.line, self.column)
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 /// A compiled brainfuck program.
 pub struct Program(Vec<Instruction>);
 
+/// Statistics about the changes made by `Program::optimize`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OptimizationStats {
+    /// The number of instructions in the program before it was optimized, where each loop counts
+    /// as one instruction plus the instructions inside it.
+    pub instructions_before: usize,
+
+    /// The number of instructions in the program after it was optimized.
+    pub instructions_after: usize,
+
+    /// The number of loops which were replaced with a single instruction.
+    pub loops_replaced: usize,
+}
+
+impl OptimizationStats {
+    /// Gets the number of instructions removed by optimizing.
+    pub fn instructions_removed(&self) -> usize {
+        self.instructions_before - self.instructions_after
+    }
+}
+
 impl Program {
-    /// Compiles a brainfuck program.
-    pub fn new(source: &str) -> Result<Program, &'static str> {
+    /// Compiles a brainfuck program. Runs of `+` or of `-` are folded into a single instruction
+    /// which adds their total, and runs of `<` or of `>` are folded into a single instruction which
+    /// moves the pointer by their total. `[-]` and `[+]` are compiled into a single instruction
+    /// which clears the current cell.
+    ///
+    /// Runs in opposite directions are kept apart, so that compiled programs behave exactly like
+    /// their source even for cell types which don't wrap, such as `Saturating` integers. Use
+    /// `.optimize()` to fold them together.
+    pub fn new(source: &str) -> Result<Program, ParseError> {
+        Self::parse(source, None)
+    }
+
+    /// Compiles a brainfuck program like `Program::new`, but fails if any loop is nested inside more
+    /// than `max_depth` loops, counting itself. Since compiled programs are run, optimized, displayed,
+    /// and dropped recursively, this should be used instead of `Program::new` when the source is
+    /// untrusted, because a program with extremely deep nesting could otherwise overflow the stack
+    /// before `RunConfig::max_loop_depth` is ever checked.
+    pub fn new_with_max_depth(source: &str, max_depth: usize) -> Result<Program, ParseError> {
+        Self::parse(source, Some(max_depth))
+    }
+
+    /// Compiles a brainfuck program, optionally limiting how deeply its loops may be nested.
+    fn parse(source: &str, max_depth: Option<usize>) -> Result<Program, ParseError> {
         let mut all_lists: Vec<Vec<Instruction>> = Vec::new();
         let mut current_list: Vec<Instruction> = Vec::new();
 
-        for char in source.chars() {
+        // The position of each unclosed `[`, used to report which one is unmatched.
+        let mut open_brackets: Vec<(usize, usize, usize)> = Vec::new();
+        let mut line = 1;
+        let mut column = 1;
+
+        for (offset, char) in source.char_indices() {
             match char {
-                '+' => current_list.push(Instruction::Inc),
-                '-' => current_list.push(Instruction::Dec),
-                '<' => current_list.push(Instruction::Shl),
-                '>' => current_list.push(Instruction::Shr),
+                '+' => push_same_direction(&mut current_list, Instruction::Add(1)),
+                '-' => push_same_direction(&mut current_list, Instruction::Add(-1)),
+                '<' => push_same_direction(&mut current_list, Instruction::Move(-1)),
+                '>' => push_same_direction(&mut current_list, Instruction::Move(1)),
                 ',' => current_list.push(Instruction::Read),
                 '.' => current_list.push(Instruction::Write),
 
                 '[' => {
+                    if max_depth.is_some_and(|max| open_brackets.len() >= max) {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::NestedTooDeeply,
+                            offset,
+                            line,
+                            column,
+                        });
+                    }
+
                     let sub_instruction_list: Vec<Instruction> = Vec::new();
                     all_lists.push(current_list);
                     current_list = sub_instruction_list;
+                    open_brackets.push((offset, line, column));
                 }
 
                 ']' => {
                     let sub_instruction_list = current_list;
 
-                    let Some(mut last_instruction_list) = all_lists.pop() else {
-                        return Err("unmatched closing bracket");
+                    let (Some(mut last_instruction_list), Some((start, _, _))) =
+                        (all_lists.pop(), open_brackets.pop())
+                    else {
+                        return Err(ParseError {
+                            kind: ParseErrorKind::UnmatchedClosingBracket,
+                            offset,
+                            line,
+                            column,
+                        });
                     };
 
-                    last_instruction_list.push(Instruction::Repeat(sub_instruction_list));
+                    if let [Instruction::Add(step @ (1 | -1))] = sub_instruction_list[..] {
+                        last_instruction_list.push(Instruction::Clear(step));
+                    } else {
+                        last_instruction_list
+                            .push(Instruction::Repeat(sub_instruction_list, Some(start)));
+                    }
+
                     current_list = last_instruction_list;
                 }
 
                 _ => {}
             };
+
+            if char == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
         }
 
-        if !all_lists.is_empty() {
-            Err("unmatched opening bracket")
+        if let Some(&(offset, line, column)) = open_brackets.last() {
+            Err(ParseError {
+                kind: ParseErrorKind::UnmatchedOpeningBracket,
+                offset,
+                line,
+                column,
+            })
         } else {
             Ok(Program(current_list))
         }
     }
 
+    /// Optimizes this program without changing what it does. This folds together runs of additions
+    /// and pointer movements that `Program::new` couldn't, such as those created by `.merge()`, and
+    /// replaces loops which can be run in constant time with a single instruction:
+    ///
+    /// - Loops like `[-]` and `[+]` set the current cell to zero.
+    /// - Loops which only add to cells and move the pointer, end where they started, and decrement
+    ///   the current cell by exactly one per iteration move the current cell's value into other
+    ///   cells. `[->+>++<<]`, for instance, adds the current cell's value to the next cell and
+    ///   twice its value to the cell after that.
+    ///
+    /// Code generated by a `Builder` uses these loops for nearly every copy, addition, and
+    /// subtraction, so this can speed up running it dramatically. Optimizing an already optimized
+    /// program changes nothing.
+    ///
+    /// These changes assume cells wrap around, as `CellValue::WRAPS` describes. Programs which will
+    /// be run with cells that saturate, such as `Saturating` integers or `bool`, should not be
+    /// optimized.
+    pub fn optimize(self) -> (Program, OptimizationStats) {
+        fn add_mul(list: &[Instruction]) -> Option<Vec<(isize, isize)>> {
+            let mut pointer = 0;
+            let mut offsets: Vec<(isize, isize)> = Vec::new();
+
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => {
+                        match offsets.iter_mut().find(|(offset, _)| *offset == pointer) {
+                            Some((_, factor)) => *factor += amount,
+                            None => offsets.push((pointer, *amount)),
+                        }
+                    }
+                    Instruction::Move(offset) => pointer += offset,
+                    _ => return None,
+                }
+            }
+
+            if pointer != 0 {
+                return None;
+            }
+
+            let index = offsets.iter().position(|(offset, _)| *offset == 0)?;
+
+            if offsets.remove(index).1 != -1 {
+                return None;
+            }
+
+            offsets.retain(|(_, factor)| *factor != 0);
+            offsets.sort();
+            Some(offsets)
+        }
+
+        fn optimize(list: Vec<Instruction>, stats: &mut OptimizationStats) -> Vec<Instruction> {
+            let mut output = Vec::new();
+
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => push_add(&mut output, amount),
+                    Instruction::Move(offset) => push_move(&mut output, offset),
+                    Instruction::Repeat(list, start) => {
+                        let list = optimize(list, stats);
+
+                        if let [Instruction::Add(step @ (1 | -1))] = list[..] {
+                            stats.loops_replaced += 1;
+                            output.push(Instruction::Clear(step));
+                        } else if let Some(offsets) = add_mul(&list) {
+                            stats.loops_replaced += 1;
+                            output.push(Instruction::AddMul(offsets));
+                        } else {
+                            output.push(Instruction::Repeat(list, start));
+                        }
+                    }
+                    instruction => output.push(instruction),
+                }
+            }
+
+            output
+        }
+
+        let mut stats = OptimizationStats {
+            instructions_before: self.instruction_count(),
+            ..OptimizationStats::default()
+        };
+
+        let program = Program(optimize(self.0, &mut stats));
+        stats.instructions_after = program.instruction_count();
+
+        (program, stats)
+    }
+
+    /// Counts the instructions in this program, where each loop counts as one instruction plus the
+    /// instructions inside it.
+    fn instruction_count(&self) -> usize {
+        fn count(list: &[Instruction]) -> usize {
+            list.iter()
+                .map(|instruction| match instruction {
+                    Instruction::Repeat(list, _) => 1 + count(list),
+                    _ => 1,
+                })
+                .sum()
+        }
+
+        count(&self.0)
+    }
+
+    /// Creates a program which runs the instructions of this one in reverse order, with every `<`
+    /// swapped for `>` and vice versa. Loops keep their position but have their contents reversed
+    /// the same way.
+    ///
+    /// This is only meaningful for programs which just move the pointer around, in which case
+    /// running a program followed by its reverse leaves the pointer where it started. Other
+    /// instructions are kept as-is, so reversing a program which changes memory generally produces
+    /// a program which does something unrelated.
+    pub fn reversed(&self) -> Program {
+        fn reverse(list: &[Instruction]) -> Vec<Instruction> {
+            list.iter()
+                .rev()
+                .map(|instruction| match instruction {
+                    Instruction::Move(offset) => Instruction::Move(-offset),
+                    Instruction::Repeat(list, start) => Instruction::Repeat(reverse(list), *start),
+                    instruction => instruction.clone(),
+                })
+                .collect()
+        }
+
+        Program(reverse(&self.0))
+    }
+
+    /// Appends another program to the end of this one. Both programs will share the same memory, so
+    /// use `.merge_relocated()` if they might use the same cells.
+    pub fn merge(mut self, other: Program) -> Program {
+        self.0.extend(other.0);
+        self
+    }
+
+    /// Appends another program to the end of this one, moving the pointer past every cell this
+    /// program could have touched before `other` starts. This lets two independently built programs
+    /// run one after the other without clobbering each other's memory, as long as `other` never
+    /// moves left of where it started.
+    ///
+    /// Fails if the cells this program touches cannot be determined without running it, which
+    /// happens when one of its loops changes the position of the pointer.
+    pub fn merge_relocated(mut self, other: Program) -> Result<Program, &'static str> {
+        let Some((pointer, high_water_mark)) = extent(&self.0) else {
+            return Err("cannot relocate after a loop which moves the pointer");
+        };
+
+        self.0
+            .push(Instruction::Move(high_water_mark + 1 - pointer));
+
+        Ok(self.merge(other))
+    }
+
+    /// Creates a program which reads a single command byte and runs the program paired with it, or
+    /// nothing if no program matches. If several programs share a command byte, only the first one
+    /// is run.
+    ///
+    /// The command byte is kept in the first cell, and the next two cells are used as scratch
+    /// space. Each program starts on the fourth cell with the rest of the input still available to
+    /// it, and must never move left of where it started.
+    ///
+    /// Fails if any program ends at a position which cannot be determined without running it, which
+    /// happens when one of its loops changes the position of the pointer.
+    pub fn dispatch(programs: &[(u8, Program)]) -> Result<Program, &'static str> {
+        let mut instructions = Program::from(",").0;
+        let mut commands = Vec::new();
+
+        for (command, program) in programs {
+            if commands.contains(command) {
+                continue;
+            }
+
+            commands.push(*command);
+
+            let Some((pointer, _)) = extent(&program.0) else {
+                return Err("cannot dispatch to a program with a loop which moves the pointer");
+            };
+
+            // Sets the second cell to 1, then clears it unless the first cell equals `command`,
+            // using the third cell to restore the first cell's value.
+            let command = usize::from(*command);
+            let check = format!(
+                ">[-]+<{}[->>+<<]>>[-<<+>><[-]>]<<{}>",
+                "-".repeat(command),
+                "+".repeat(command),
+            );
+
+            let mut body = Program::from("->>").0;
+            body.extend(program.0.iter().cloned());
+            push_move(&mut body, -pointer - 2);
+
+            instructions.extend(Program::from(check.as_str()).0);
+            instructions.push(Instruction::Repeat(body, None));
+            push_move(&mut instructions, -1);
+        }
+
+        Ok(Program(instructions))
+    }
+
+    /// Gets the bytes this program writes if it never reads input, since its output is then the same
+    /// every time it runs. The program is run once like with `run_bytes`, so this never returns if
+    /// the program never finishes. Returns `None` if the program reads input or stops with an
+    /// error.
+    pub fn constant_output(&self) -> Option<Vec<u8>> {
+        fn reads(list: &[Instruction]) -> bool {
+            list.iter().any(|instruction| match instruction {
+                Instruction::Read => true,
+                Instruction::Repeat(list, _) => reads(list),
+                _ => false,
+            })
+        }
+
+        if reads(&self.0) {
+            return None;
+        }
+
+        let runner = self
+            .try_run::<RUN_BYTES_TAPE_SIZE, _, _, _>(
+                std::iter::empty::<Wrapping<u8>>(),
+                Vec::new(),
+                RunConfig::default(),
+            )
+            .ok()?;
+
+        Some(runner.output().iter().map(|value| value.0).collect())
+    }
+
+    /// Gets the number of loops in this program at any depth. Loops replaced with a single
+    /// instruction, such as `[-]`, are still counted.
+    pub fn loop_count(&self) -> usize {
+        fn count(list: &[Instruction]) -> usize {
+            list.iter()
+                .map(|instruction| match instruction {
+                    Instruction::Repeat(list, _) => 1 + count(list),
+                    Instruction::Clear(_) | Instruction::AddMul(_) => 1,
+                    _ => 0,
+                })
+                .sum()
+        }
+
+        count(&self.0)
+    }
+
+    /// Gets the greatest number of loops nested inside each other in this program.
+    pub fn loop_depth(&self) -> usize {
+        // This is iterative so that checking deeply nested programs can't overflow the stack.
+        let mut max_depth = 0;
+        let mut lists = vec![(&self.0, 0)];
+
+        while let Some((list, depth)) = lists.pop() {
+            max_depth = max_depth.max(depth);
+
+            for instruction in list {
+                match instruction {
+                    Instruction::Repeat(list, _) => lists.push((list, depth + 1)),
+                    Instruction::Clear(_) | Instruction::AddMul(_) => {
+                        max_depth = max_depth.max(depth + 1)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        max_depth
+    }
+
+    /// Estimates how expensive this program is to run, assuming every loop runs `loop_iterations`
+    /// times. Each instruction costs 1, and each loop costs 1 plus the cost of checking its
+    /// condition and running its body on every iteration.
+    ///
+    /// This is only a heuristic for comparing programs without running them, since the actual
+    /// number of times a loop runs depends on the program's input and memory.
+    pub fn estimate_cost(&self, loop_iterations: u64) -> u64 {
+        fn cost(list: &[Instruction], loop_iterations: u64) -> u64 {
+            list.iter()
+                .map(|instruction| match instruction {
+                    Instruction::Repeat(list, _) => cost(list, loop_iterations)
+                        .saturating_add(1)
+                        .saturating_mul(loop_iterations)
+                        .saturating_add(1),
+                    _ => 1,
+                })
+                .fold(0, u64::saturating_add)
+        }
+
+        cost(&self.0, loop_iterations)
+    }
+
+    /// Translates this program into a standalone C program whose memory tape holds `tape_size`
+    /// unsigned integers of `cell_bits` bits each. Like `Runner`, the generated program leaves the
+    /// current cell unchanged when reading past the end of input.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `cell_bits` is not 8, 16, 32, or 64.
+    pub fn to_c(&self, cell_bits: u8, tape_size: usize) -> String {
+        fn write(list: &[Instruction], depth: usize, output: &mut String) {
+            let indent = "    ".repeat(depth);
+
+            for instruction in list {
+                let line = match instruction {
+                    Instruction::Add(amount) if *amount < 0 => {
+                        format!("mem[p] -= {};", amount.unsigned_abs())
+                    }
+                    Instruction::Add(amount) => format!("mem[p] += {amount};"),
+                    Instruction::Move(offset) if *offset < 0 => {
+                        format!("p -= {};", offset.unsigned_abs())
+                    }
+                    Instruction::Move(offset) => format!("p += {offset};"),
+                    Instruction::Read => {
+                        "{ int c = getchar(); if (c != EOF) mem[p] = c; }".to_owned()
+                    }
+                    Instruction::Write => "putchar(mem[p]);".to_owned(),
+                    Instruction::Clear(_) => "mem[p] = 0;".to_owned(),
+                    Instruction::AddMul(offsets) => {
+                        for &(offset, factor) in offsets {
+                            output.push_str(&format!(
+                                "{indent}{} += mem[p] * {factor};\n",
+                                tape_cell(offset)
+                            ));
+                        }
+
+                        "mem[p] = 0;".to_owned()
+                    }
+                    Instruction::Repeat(list, _) => {
+                        output.push_str(&format!("{indent}while (mem[p]) {{\n"));
+                        write(list, depth + 1, output);
+                        "}".to_owned()
+                    }
+                };
+
+                output.push_str(&format!("{indent}{line}\n"));
+            }
+        }
+
+        if ![8, 16, 32, 64].contains(&cell_bits) {
+            panic!("cannot translate to C with cells of {cell_bits} bits");
+        }
+
+        let mut output = format!(
+            "#include <stdint.h>\n\
+             #include <stdio.h>\n\
+             \n\
+             static uint{cell_bits}_t mem[{tape_size}];\n\
+             \n\
+             int main(void) {{\n    \
+                 size_t p = 0;\n\n"
+        );
+
+        write(&self.0, 1, &mut output);
+        output.push_str("    return 0;\n}\n");
+        output
+    }
+
+    /// Translates this program into the source of a Rust function called `run`, which runs the
+    /// program on a memory tape of `N` cells of any `CellValue` type and returns the tape once it
+    /// finishes. The function reads from a `RunnerInput` and writes to a `RunnerOutput`, so
+    /// `CellValue`, `RunnerInput`, and `RunnerOutput` must be in scope wherever it is used. Like
+    /// `Runner`, it leaves the current cell unchanged when reading past the end of input.
+    pub fn to_rust(&self) -> String {
+        fn write(list: &[Instruction], depth: usize, output: &mut String) {
+            let indent = "    ".repeat(depth);
+
+            for instruction in list {
+                let line = match instruction {
+                    Instruction::Add(amount) => format!("mem[p] = mem[p].offset({amount});"),
+                    Instruction::Move(offset) if *offset < 0 => {
+                        format!("p -= {};", offset.unsigned_abs())
+                    }
+                    Instruction::Move(offset) => format!("p += {offset};"),
+                    Instruction::Read => {
+                        "if let Some(value) = input.read() { mem[p] = value; }".to_owned()
+                    }
+                    Instruction::Write => "output.write(mem[p]);".to_owned(),
+                    Instruction::Clear(_) => "mem[p] = T::ZERO;".to_owned(),
+                    Instruction::AddMul(offsets) => {
+                        for &(offset, factor) in offsets {
+                            let cell = tape_cell(offset);
+                            output.push_str(&format!(
+                                "{indent}{cell} = {cell}.add_product(mem[p], {factor});\n"
+                            ));
+                        }
+
+                        "mem[p] = T::ZERO;".to_owned()
+                    }
+                    Instruction::Repeat(list, _) => {
+                        output.push_str(&format!("{indent}while mem[p] != T::ZERO {{\n"));
+                        write(list, depth + 1, output);
+                        "}".to_owned()
+                    }
+                };
+
+                output.push_str(&format!("{indent}{line}\n"));
+            }
+        }
+
+        fn any(list: &[Instruction], predicate: fn(&Instruction) -> bool) -> bool {
+            list.iter().any(|instruction| match instruction {
+                Instruction::Repeat(list, _) => any(list, predicate),
+                instruction => predicate(instruction),
+            })
+        }
+
+        // Moves at the end of the program can't change the tape it returns, so they're left out.
+        let end = self
+            .0
+            .iter()
+            .rposition(|instruction| !matches!(instruction, Instruction::Move(_)))
+            .map_or(0, |index| index + 1);
+        let list = &self.0[..end];
+
+        // Only mark things as mutable if they're used, so that the function compiles without
+        // warnings.
+        let input = if any(list, |instruction| matches!(instruction, Instruction::Read)) {
+            "mut input"
+        } else {
+            "_input"
+        };
+        let output = if any(list, |instruction| {
+            matches!(instruction, Instruction::Write)
+        }) {
+            "mut output"
+        } else {
+            "_output"
+        };
+        let memory = if any(list, |instruction| {
+            matches!(
+                instruction,
+                Instruction::Add(_)
+                    | Instruction::Read
+                    | Instruction::Clear(_)
+                    | Instruction::AddMul(_)
+            )
+        }) {
+            "mut mem"
+        } else {
+            "mem"
+        };
+        let pointer = if any(list, |instruction| {
+            matches!(instruction, Instruction::Move(_))
+        }) {
+            "mut p"
+        } else if list.is_empty() {
+            "_p"
+        } else {
+            "p"
+        };
+
+        let mut code = format!(
+            "pub fn run<const N: usize, T: CellValue>(\n    \
+                 {input}: impl RunnerInput<T>,\n    \
+                 {output}: impl RunnerOutput<T>,\n\
+             ) -> [T; N] {{\n    \
+                 let {memory} = [T::ZERO; N];\n    \
+                 let {pointer} = 0usize;\n\n"
+        );
+
+        write(list, 1, &mut code);
+        code.push_str("\n    mem\n}\n");
+        code
+    }
+
+    /// Flattens this program into a list of steps, replacing each loop with a pair of jumps.
+    pub(crate) fn flatten(&self) -> Vec<Step> {
+        fn flatten(list: &[Instruction], steps: &mut Vec<Step>) {
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => steps.push(Step::Add(*amount)),
+                    Instruction::Move(offset) => steps.push(Step::Move(*offset)),
+                    Instruction::Read => steps.push(Step::Read),
+                    Instruction::Write => steps.push(Step::Write),
+                    Instruction::Clear(step) => steps.push(Step::Clear(*step)),
+                    Instruction::AddMul(offsets) => {
+                        // Expanding this back into a loop keeps every step small enough to undo.
+                        let start = steps.len();
+                        steps.push(Step::JumpIfZero(0));
+                        steps.push(Step::Add(-1));
+
+                        let mut pointer = 0;
+
+                        for (offset, factor) in offsets {
+                            steps.push(Step::Move(offset - pointer));
+                            steps.push(Step::Add(*factor));
+                            pointer = *offset;
+                        }
+
+                        steps.push(Step::Move(-pointer));
+                        let end = steps.len();
+                        steps.push(Step::JumpIfNonzero(start));
+                        steps[start] = Step::JumpIfZero(end);
+                    }
+                    Instruction::Repeat(list, _) => {
+                        let start = steps.len();
+                        steps.push(Step::JumpIfZero(0));
+                        flatten(list, steps);
+                        let end = steps.len();
+                        steps.push(Step::JumpIfNonzero(start));
+                        steps[start] = Step::JumpIfZero(end);
+                    }
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        flatten(&self.0, &mut steps);
+        steps
+    }
+
     /// Runs this program on a given runner.
-    pub fn run_on<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue>(
+    pub fn run_on<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
         &self,
         runner: &mut Runner<N, I, O, T>,
     ) {
-        fn run<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue>(
+        fn run<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
             list: &Vec<Instruction>,
             runner: &mut Runner<N, I, O, T>,
         ) {
             for instruction in list {
                 match instruction {
-                    Instruction::Inc => runner.inc(),
-                    Instruction::Dec => runner.dec(),
-                    Instruction::Shl => runner.shl(),
-                    Instruction::Shr => runner.shr(),
+                    Instruction::Add(amount) => runner.add(*amount),
+                    Instruction::Move(offset) => runner.shift(*offset),
                     Instruction::Read => runner.read(),
                     Instruction::Write => runner.write(),
-                    Instruction::Repeat(list) => runner.repeat(|runner| run(list, runner)),
+                    Instruction::Repeat(list, _) => runner.repeat(|runner| run(list, runner)),
+                    Instruction::Clear(step) => runner.clear(*step),
+                    Instruction::AddMul(offsets) => runner.add_mul(offsets),
                 }
             }
         }
@@ -89,7 +849,7 @@ impl Program {
     }
 
     /// Runs this program on a new runner.
-    pub fn run<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue>(
+    pub fn run<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
         &self,
         input: I,
         output: O,
@@ -98,4 +858,1128 @@ impl Program {
         self.run_on(&mut runner);
         runner
     }
+
+    /// Runs this program on a given runner, counting how many times the body of each loop runs.
+    /// Returns the counts keyed by the byte offset of each loop's `[` in the source it was compiled
+    /// from, so loops which never ran are left out.
+    ///
+    /// Loops which compile to a single instruction, such as `[-]`, `[+]`, and anything replaced by
+    /// `.optimize()`, are not counted. Programs combined with `.merge()` or
+    /// `Program::dispatch` may have several loops at the same offset, whose counts are added
+    /// together.
+    pub fn run_profiled_on<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+        &self,
+        runner: &mut Runner<N, I, O, T>,
+    ) -> HashMap<usize, u64> {
+        fn run<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+            list: &Vec<Instruction>,
+            runner: &mut Runner<N, I, O, T>,
+            counts: &mut HashMap<usize, u64>,
+        ) {
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => runner.add(*amount),
+                    Instruction::Move(offset) => runner.shift(*offset),
+                    Instruction::Read => runner.read(),
+                    Instruction::Write => runner.write(),
+                    Instruction::Repeat(list, start) => runner.repeat(|runner| {
+                        if let Some(start) = start {
+                            *counts.entry(*start).or_default() += 1;
+                        }
+
+                        run(list, runner, counts)
+                    }),
+                    Instruction::Clear(step) => runner.clear(*step),
+                    Instruction::AddMul(offsets) => runner.add_mul(offsets),
+                }
+            }
+        }
+
+        let mut counts = HashMap::new();
+        run(&self.0, runner, &mut counts);
+        counts
+    }
+
+    /// Runs this program on a given dynamic runner, whose memory grows as the program uses it.
+    pub fn run_on_dyn<I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+        &self,
+        runner: &mut DynRunner<I, O, T>,
+    ) {
+        fn run<I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+            list: &Vec<Instruction>,
+            runner: &mut DynRunner<I, O, T>,
+        ) {
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => runner.add(*amount),
+                    Instruction::Move(offset) => runner.shift(*offset),
+                    Instruction::Read => runner.read(),
+                    Instruction::Write => runner.write(),
+                    Instruction::Repeat(list, _) => runner.repeat(|runner| run(list, runner)),
+                    Instruction::Clear(step) => runner.clear(*step),
+                    Instruction::AddMul(offsets) => runner.add_mul(offsets),
+                }
+            }
+        }
+
+        run(&self.0, runner)
+    }
+
+    /// Runs this program on a new dynamic runner, whose memory grows as the program uses it.
+    pub fn run_dyn<I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+        &self,
+        input: I,
+        output: O,
+    ) -> DynRunner<I, O, T> {
+        let mut runner = DynRunner::new(input, output);
+        self.run_on_dyn(&mut runner);
+        runner
+    }
+
+    /// Runs this program on a given runner, stopping with an error if any of the limits in the
+    /// runner's configuration are exceeded or if the pointer moves outside of memory.
+    pub fn try_run_on<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+        &self,
+        runner: &mut Runner<N, I, O, T>,
+    ) -> Result<(), RunError> {
+        if runner
+            .config()
+            .max_loop_depth
+            .is_some_and(|max| self.loop_depth() > max)
+        {
+            return Err(RunError::LoopDepthExceeded);
+        }
+
+        fn run<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+            list: &Vec<Instruction>,
+            runner: &mut Runner<N, I, O, T>,
+        ) -> Result<(), RunError> {
+            for instruction in list {
+                // Folded runs count one step per character. Loops count their own steps each time
+                // they check their condition, and instructions which replace loops count the steps
+                // those loops would have taken.
+                match instruction {
+                    Instruction::Add(amount) => {
+                        runner.try_steps(amount.unsigned_abs() as u64)?;
+                        runner.add(*amount);
+                    }
+                    Instruction::Move(offset) => {
+                        runner.try_steps(offset.unsigned_abs() as u64)?;
+                        runner.try_shift(*offset)?;
+                    }
+                    Instruction::Read => {
+                        runner.try_step()?;
+                        runner.read();
+                    }
+                    Instruction::Write => {
+                        runner.try_step()?;
+                        runner.try_write()?;
+                    }
+                    Instruction::Repeat(list, _) => {
+                        runner.try_repeat(|runner| run(list, runner))?
+                    }
+                    Instruction::Clear(step) => runner.try_clear(*step)?,
+                    Instruction::AddMul(offsets) => runner.try_add_mul(offsets)?,
+                }
+            }
+
+            Ok(())
+        }
+
+        run(&self.0, runner)
+    }
+
+    /// Runs this program on a new runner with a given configuration, stopping with an error if any
+    /// of its limits are exceeded.
+    pub fn try_run<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue>(
+        &self,
+        input: I,
+        output: O,
+        config: RunConfig,
+    ) -> Result<Runner<N, I, O, T>, RunError> {
+        let mut runner = Runner::with_config(input, output, config);
+        self.try_run_on(&mut runner)?;
+        Ok(runner)
+    }
+}
+
+impl fmt::Display for Program {
+    /// Writes brainfuck source which compiles back into this program. Instructions created by
+    /// `Program::optimize` are written as the loops they replaced.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn repeat(
+            f: &mut fmt::Formatter<'_>,
+            positive: &str,
+            negative: &str,
+            count: isize,
+        ) -> fmt::Result {
+            let char = if count < 0 { negative } else { positive };
+            f.write_str(&char.repeat(count.unsigned_abs()))
+        }
+
+        fn write(list: &[Instruction], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for instruction in list {
+                match instruction {
+                    Instruction::Add(amount) => repeat(f, "+", "-", *amount)?,
+                    Instruction::Move(offset) => repeat(f, ">", "<", *offset)?,
+                    Instruction::Read => f.write_str(",")?,
+                    Instruction::Write => f.write_str(".")?,
+                    Instruction::Repeat(list, _) => {
+                        f.write_str("[")?;
+                        write(list, f)?;
+                        f.write_str("]")?;
+                    }
+                    Instruction::Clear(step) => {
+                        f.write_str("[")?;
+                        repeat(f, "+", "-", *step)?;
+                        f.write_str("]")?;
+                    }
+                    Instruction::AddMul(offsets) => {
+                        f.write_str("[-")?;
+
+                        let mut pointer = 0;
+
+                        for (offset, factor) in offsets {
+                            repeat(f, ">", "<", offset - pointer)?;
+                            repeat(f, "+", "-", *factor)?;
+                            pointer = *offset;
+                        }
+
+                        repeat(f, ">", "<", -pointer)?;
+                        f.write_str("]")?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        write(&self.0, f)
+    }
+}
+
+impl From<&str> for Program {
+    /// Compiles a brainfuck program. This is a convenience for tests and examples where the source
+    /// is known to be valid; use `Program::new` to handle errors instead.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the program has unmatched brackets.
+    fn from(source: &str) -> Self {
+        match Program::new(source) {
+            Ok(program) => program,
+            Err(error) => panic!("{error}"),
+        }
+    }
+}
+
+/// An error which stopped a brainfuck program from being compiled or run to completion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BfError {
+    /// The program could not be compiled.
+    Parse(ParseError),
+
+    /// The program stopped with an error while running.
+    Run(RunError),
+}
+
+impl fmt::Display for BfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BfError::Parse(error) => error.fmt(f),
+            BfError::Run(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for BfError {}
+
+impl From<ParseError> for BfError {
+    fn from(error: ParseError) -> Self {
+        BfError::Parse(error)
+    }
+}
+
+impl From<RunError> for BfError {
+    fn from(error: RunError) -> Self {
+        BfError::Run(error)
+    }
+}
+
+/// Compiles and runs a brainfuck program on some input bytes, returning the bytes it writes. The
+/// program runs on a tape of 30,000 wrapping `u8` cells.
+pub fn run_bytes(source: &str, input: &[u8]) -> Result<Vec<u8>, BfError> {
+    let program = Program::new(source)?;
+
+    let runner = program.try_run::<RUN_BYTES_TAPE_SIZE, _, _, _>(
+        input.iter().copied().map(Wrapping),
+        Vec::new(),
+        RunConfig::default(),
+    )?;
+
+    Ok(runner.output().iter().map(|value| value.0).collect())
+}
+
+/// An error which stopped macros from being expanded by `expand_macros`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExpandError {
+    /// A macro was defined for one of the eight brainfuck commands, which can't be overridden.
+    ReservedName(char),
+
+    /// Macros were nested too deeply while expanding the given macro, which usually means it
+    /// expands into itself.
+    DepthExceeded(char),
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpandError::ReservedName(name) => {
+                write!(
+                    f,
+                    "cannot define a macro named {name:?}, which is a brainfuck command"
+                )
+            }
+            ExpandError::DepthExceeded(name) => {
+                write!(f, "macros nested too deeply while expanding {name:?}")
+            }
+        }
+    }
+}
+
+impl Error for ExpandError {}
+
+/// Replaces each character of `source` which names a macro with the macro's expansion, which may
+/// itself use other macros. Characters which aren't macros are kept as-is, so the result can be
+/// passed straight to `Program::new`.
+pub fn expand_macros(source: &str, macros: &HashMap<char, &str>) -> Result<String, ExpandError> {
+    fn expand(
+        source: &str,
+        macros: &HashMap<char, &str>,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), ExpandError> {
+        for char in source.chars() {
+            match macros.get(&char) {
+                Some(_) if depth == MAX_MACRO_DEPTH => {
+                    return Err(ExpandError::DepthExceeded(char))
+                }
+                Some(expansion) => expand(expansion, macros, depth + 1, output)?,
+                None => output.push(char),
+            }
+        }
+
+        Ok(())
+    }
+
+    if let Some(&name) = macros.keys().find(|name| "+-<>,.[]".contains(**name)) {
+        return Err(ExpandError::ReservedName(name));
+    }
+
+    let mut output = String::new();
+    expand(source, macros, 0, &mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::Saturating;
+
+    /// Runs a program on a tape of 16 `u8` cells with no input.
+    fn run(program: &Program) -> Runner<16, std::iter::Empty<u8>, Vec<u8>, u8> {
+        program.run(std::iter::empty(), Vec::new())
+    }
+
+    #[test]
+    fn merge_relocated_keeps_fragments_apart() {
+        let first = Program::from("+++>++");
+        let second = Program::from(">+++[<++>-]<.");
+
+        let runner = run(&first.clone().merge_relocated(second.clone()).unwrap());
+        assert_eq!(runner.output(), &[6]);
+        assert_eq!(runner.memory()[..4], [3, 2, 6, 0]);
+
+        // Without relocation, the second fragment reuses the first fragment's cells.
+        let runner = run(&first.merge(second));
+        assert_eq!(runner.memory()[..3], [3, 8, 0]);
+    }
+
+    #[test]
+    fn merge_relocated_rejects_loops_which_move_the_pointer() {
+        assert!(Program::from("+[>]")
+            .merge_relocated(Program::from("+"))
+            .is_err());
+    }
+
+    /// Runs a program on a tape of 16 `u8` cells with no input and the given configuration.
+    fn try_run(
+        program: &str,
+        config: RunConfig,
+    ) -> Result<Runner<16, std::iter::Empty<u8>, Vec<u8>, u8>, RunError> {
+        Program::from(program).try_run(std::iter::empty(), Vec::new(), config)
+    }
+
+    #[test]
+    fn max_writes_stops_runaway_output() {
+        let config = RunConfig {
+            max_writes: Some(3),
+            ..RunConfig::default()
+        };
+
+        let error = try_run("+[.]", config.clone()).unwrap_err();
+        assert_eq!(error, RunError::OutputLimitExceeded);
+
+        let runner = try_run("+++[.-]", config).unwrap();
+        assert_eq!(runner.output(), &[3, 2, 1]);
+    }
+
+    #[test]
+    fn new_with_max_depth_rejects_deep_nesting_without_overflowing() {
+        let depth = 1_000_000;
+        let source = "[".repeat(depth) + &"]".repeat(depth);
+
+        let error = Program::new_with_max_depth(&source, 10).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::NestedTooDeeply);
+        assert_eq!(error.offset, 10);
+
+        assert!(Program::new_with_max_depth("[[[]]][[]]", 3).is_ok());
+    }
+
+    #[test]
+    fn max_loop_depth_stops_deeply_nested_programs() {
+        let source = "+".to_owned() + &"[".repeat(20) + &"-]".repeat(20);
+        let config = RunConfig {
+            max_loop_depth: Some(10),
+            ..RunConfig::default()
+        };
+
+        let error = try_run(&source, config.clone()).unwrap_err();
+        assert_eq!(error, RunError::LoopDepthExceeded);
+        assert!(try_run("+[[-]]", config).is_ok());
+    }
+
+    #[test]
+    fn estimate_cost_prefers_straight_line_code() {
+        let looped = Program::new("+++[>+<-]").unwrap();
+        let straight = Program::new(">+++").unwrap();
+
+        for loop_iterations in [1, 3, 10] {
+            assert!(
+                looped.estimate_cost(loop_iterations) > straight.estimate_cost(loop_iterations)
+            );
+        }
+    }
+
+    #[test]
+    fn from_str_compiles_valid_programs() {
+        let program = Program::from("++++++[>+++++++<-]>.");
+        assert_eq!(program, Program::new("++++++[>+++++++<-]>.").unwrap());
+        assert_eq!(run(&program).into_output(), [42]);
+    }
+
+    #[test]
+    #[should_panic(expected = "unmatched opening bracket")]
+    fn from_str_panics_on_invalid_programs() {
+        let _ = Program::from("+[");
+    }
+
+    #[test]
+    fn saturating_cells_match_step_by_step_execution() {
+        let run = |source: &str| {
+            Program::from(source)
+                .run::<4, _, _, Saturating<u8>>(std::iter::empty(), Vec::new())
+                .cell_value(0)
+        };
+
+        assert_eq!(run(&"+".repeat(300)), Saturating(255));
+        assert_eq!(run(&format!("{}-", "+".repeat(300))), Saturating(254));
+        assert_eq!(run(&format!("{}[-]", "+".repeat(300))), Saturating(0));
+        assert_eq!(run("--+"), Saturating(1));
+    }
+
+    #[test]
+    fn folding_runs_shrinks_programs_without_changing_output() {
+        let source = format!("{}>{}<.>.<+-.", "+".repeat(200), "+".repeat(100));
+        let program = Program::from(&*source);
+
+        assert_eq!(program.instruction_count(), 11);
+        assert!(program.instruction_count() * 25 < source.len());
+        assert_eq!(run(&program).into_output(), [200, 100, 200]);
+    }
+
+    /// Runs brainfuck source one character at a time on a tape of 16 wrapping `u8` cells, without
+    /// compiling it, and returns its output and final memory.
+    fn run_naive(source: &str) -> (Vec<u8>, [u8; 16]) {
+        let source = source.as_bytes();
+        let mut memory = [0u8; 16];
+        let mut pointer = 0;
+        let mut output = Vec::new();
+        let mut index = 0;
+
+        while index < source.len() {
+            match source[index] {
+                b'+' => memory[pointer] = memory[pointer].wrapping_add(1),
+                b'-' => memory[pointer] = memory[pointer].wrapping_sub(1),
+                b'>' => pointer += 1,
+                b'<' => pointer -= 1,
+                b'.' => output.push(memory[pointer]),
+                b'[' if memory[pointer] == 0 => {
+                    let mut depth = 0;
+
+                    loop {
+                        match source[index] {
+                            b'[' => depth += 1,
+                            b']' => depth -= 1,
+                            _ => {}
+                        }
+
+                        if depth == 0 {
+                            break;
+                        }
+
+                        index += 1;
+                    }
+                }
+                b']' if memory[pointer] != 0 => {
+                    let mut depth = 0;
+
+                    loop {
+                        match source[index] {
+                            b'[' => depth -= 1,
+                            b']' => depth += 1,
+                            _ => {}
+                        }
+
+                        if depth == 0 {
+                            break;
+                        }
+
+                        index -= 1;
+                    }
+                }
+                _ => {}
+            }
+
+            index += 1;
+        }
+
+        (output, memory)
+    }
+
+    #[test]
+    fn clear_matches_naive_interpreter() {
+        for source in [
+            "+++++[-].",
+            "[-]+.",
+            "++>+++[-]<[-].>.",
+            "+++[>+++++[-]+<-]>.",
+            "++[>+++[>[-]++<-]<-]>>.",
+        ] {
+            let program = Program::from(source);
+            let runner = run(&program);
+            let (output, memory) = run_naive(source);
+
+            assert_eq!(runner.output(), &output, "{source}");
+            assert_eq!(runner.memory(), &memory, "{source}");
+        }
+    }
+
+    #[test]
+    fn clear_is_detected_inside_other_loops() {
+        let program = Program::from("+[>[-]<-]");
+        let Instruction::Repeat(list, _) = &program.0[1] else {
+            panic!("expected a loop");
+        };
+
+        assert_eq!(program.0[0], Instruction::Add(1));
+        assert_eq!(
+            list[..],
+            [
+                Instruction::Move(1),
+                Instruction::Clear(-1),
+                Instruction::Move(-1),
+                Instruction::Add(-1),
+            ]
+        );
+    }
+
+    #[test]
+    fn bool_cells_match_step_by_step_execution() {
+        let run = |source: &str| {
+            Program::from(source)
+                .run::<4, _, _, bool>(std::iter::empty(), Vec::new())
+                .into_output()
+        };
+
+        assert_eq!(run("++-."), [false]);
+        assert_eq!(run("--+."), [true]);
+        assert_eq!(run("+[-].+.>+++[>+<-]>."), [false, true, true]);
+    }
+
+    #[test]
+    fn add_mul_matches_unoptimized_builder_output() {
+        use crate::builder::core::Builder;
+
+        let add = Builder::<16, Wrapping<u8>>::new();
+        let mut a = add.cell(Wrapping(200));
+        let b = add.cell(Wrapping(100));
+        a += &b;
+        a.write();
+        b.write();
+
+        let copy = Builder::<16, Wrapping<u8>>::new();
+        let original = copy.cell(Wrapping(42));
+        let copies = original.copy::<3>();
+        original.write();
+        copies.iter().for_each(|cell| cell.write());
+
+        for builder in [&add, &copy] {
+            let program = builder.compile().unwrap();
+            let (optimized, stats) = program.clone().optimize();
+            assert!(stats.loops_replaced > 0);
+
+            let expected = program.run::<16, _, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+            let actual = optimized.run::<16, _, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+            assert_eq!(actual.output(), expected.output());
+            assert_eq!(actual.memory(), expected.memory());
+        }
+    }
+
+    #[test]
+    fn optimize_is_idempotent_and_preserves_behavior() {
+        use crate::builder::core::Builder;
+
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut a = builder.cell(Wrapping(200));
+        let b = builder.cell(Wrapping(7));
+        a /= &b;
+        a.write();
+        let mut c = builder.cell(Wrapping(13));
+        c *= &b;
+        c.write();
+        a.min(&c).write();
+
+        let programs = [
+            builder.compile().unwrap(),
+            Builder::<16, Wrapping<u8>>::new().reverse_input_program(4),
+            Program::from("++>+++++[<+>-]<[->>+>++<<<]>>>.<."),
+        ];
+
+        for program in programs {
+            let (optimized, _) = program.clone().optimize();
+            let (twice, stats) = optimized.clone().optimize();
+            assert_eq!(twice, optimized);
+            assert_eq!(stats.instructions_removed(), 0);
+            assert_eq!(stats.loops_replaced, 0);
+
+            let input = *b"abcd";
+            let expected =
+                program.run::<16, _, _, Wrapping<u8>>(input.map(Wrapping).into_iter(), Vec::new());
+            let actual = optimized
+                .run::<16, _, _, Wrapping<u8>>(input.map(Wrapping).into_iter(), Vec::new());
+            assert_eq!(actual.output(), expected.output());
+            assert_eq!(actual.memory(), expected.memory());
+        }
+    }
+
+    #[test]
+    fn dispatch_runs_the_matching_program() {
+        let program = Program::dispatch(&[
+            (1, Program::from("+++.")),
+            (2, Program::from(",.>,+.")),
+            (1, Program::from("++++.")),
+            (200, Program::from(">>++.<<")),
+        ])
+        .unwrap();
+
+        let run = |input: &[u8]| {
+            program
+                .run::<16, _, _, Wrapping<u8>>(input.iter().copied().map(Wrapping), Vec::new())
+                .into_output()
+        };
+
+        assert_eq!(run(&[1]), [Wrapping(3)]);
+        assert_eq!(run(&[2, 9, 4]), [Wrapping(9), Wrapping(5)]);
+        assert_eq!(run(&[200]), [Wrapping(2)]);
+        assert_eq!(run(&[3]), []);
+        assert_eq!(run(&[0]), []);
+    }
+
+    #[test]
+    fn dispatch_rejects_programs_ending_at_unknown_positions() {
+        assert!(Program::dispatch(&[(1, Program::from("+[>+]"))]).is_err());
+    }
+
+    #[test]
+    fn optimized_programs_match_on_several_inputs() {
+        let program = Program::from(",[->++<]>+++-->>+<<<>>.,[>+>+<<-]>>[-<<+>>]<.>.");
+        let (optimized, _) = program.clone().optimize();
+
+        for input in [[0, 0], [1, 2], [100, 7], [255, 255]] {
+            let run = |program: &Program| {
+                program
+                    .run::<16, _, _, Wrapping<u8>>(input.map(Wrapping).into_iter(), Vec::new())
+                    .into_output()
+            };
+
+            assert_eq!(run(&optimized), run(&program));
+        }
+    }
+
+    #[test]
+    fn max_steps_stops_infinite_loops() {
+        let config = RunConfig {
+            max_steps: Some(1000),
+            ..RunConfig::default()
+        };
+
+        let error = try_run("+[]", config.clone()).unwrap_err();
+        assert_eq!(error, RunError::StepLimitExceeded);
+
+        let runner = try_run("+++++[>++<-]>.", config).unwrap();
+        assert_eq!(runner.output(), &[10]);
+    }
+
+    #[test]
+    fn max_steps_counts_every_character_of_folded_instructions() {
+        /// Finds the smallest step limit which lets a program finish.
+        fn steps(program: &Program) -> u64 {
+            (0..)
+                .find(|&max| {
+                    let config = RunConfig {
+                        max_steps: Some(max),
+                        ..RunConfig::default()
+                    };
+
+                    program
+                        .try_run::<16, _, _, u8>(std::iter::empty(), Vec::new(), config)
+                        .is_ok()
+                })
+                .unwrap()
+        }
+
+        assert_eq!(steps(&Program::from("++++++++++>>><<.")), 16);
+
+        // Each of 3 iterations checks the condition and runs 5 instructions, then one final check.
+        let program = Program::from("+++[->++<]");
+        assert_eq!(steps(&program), 3 + 3 * 6 + 1);
+        assert_eq!(steps(&program.optimize().0), 3 + 3 * 6 + 1);
+
+        assert_eq!(steps(&Program::from("+++[-]")), 3 + 3 * 2 + 1);
+    }
+
+    #[test]
+    fn moving_left_of_memory_is_an_error() {
+        let error = try_run("<", RunConfig::default()).unwrap_err();
+        assert_eq!(error, RunError::PointerUnderflow);
+
+        let error = try_run(">+[<+]", RunConfig::default()).unwrap_err();
+        assert_eq!(error, RunError::PointerUnderflow);
+
+        let mut runner = Runner::<4, _, _, u8>::new(std::iter::empty(), Vec::new());
+        assert_eq!(runner.try_shl(), Err(RunError::PointerUnderflow));
+    }
+
+    #[test]
+    fn clear_on_255_runs_no_loop_iterations() {
+        let program = Program::from("-[-]");
+        assert_eq!(program.0, [Instruction::Add(-1), Instruction::Clear(-1)]);
+
+        let mut runner = Runner::<4, _, _, Wrapping<u8>>::new(std::iter::empty(), Vec::new());
+        let counts = program.run_profiled_on(&mut runner);
+        assert!(counts.is_empty());
+        assert_eq!(runner.cell_value(0), Wrapping(0));
+    }
+
+    #[test]
+    fn run_bytes_echoes_every_byte() {
+        let input: Vec<u8> = (0..=255).rev().collect();
+        assert_eq!(run_bytes(&",.".repeat(256), &input).unwrap(), input);
+        assert_eq!(run_bytes(",.,.", &[0, 255]).unwrap(), [0, 255]);
+        assert!(matches!(run_bytes("[", &[]), Err(BfError::Parse(_))));
+    }
+
+    #[test]
+    fn optimized_builder_loops_match_naive_interpreter() {
+        use crate::builder::core::Builder;
+
+        let builder = Builder::<16, Wrapping<u8>>::new();
+        let mut a = builder.cell(Wrapping(12));
+        let b = builder.cell(Wrapping(25));
+        let copies = a.copy::<2>();
+        a *= &b;
+        a.write();
+        copies.iter().for_each(|cell| cell.write());
+        drop(copies);
+        drop(a);
+        drop(b);
+
+        let source = builder.source();
+        let (optimized, stats) = Program::from(&*source).optimize();
+        assert!(stats.loops_replaced > 0);
+
+        let runner = optimized.run::<16, _, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+        let (output, memory) = run_naive(&source);
+        assert_eq!(
+            runner.output(),
+            &output.into_iter().map(Wrapping).collect::<Vec<_>>()
+        );
+        assert_eq!(runner.memory(), &memory.map(Wrapping));
+    }
+
+    #[test]
+    fn display_round_trips_through_new() {
+        for source in [
+            "",
+            "+++>>--<.,",
+            "+-<>",
+            "++[>+++[>+<-]<-]>>.",
+            "[-][[]]",
+            ",[.,]",
+        ] {
+            let program = Program::from(source);
+            assert_eq!(program.to_string(), source);
+            assert_eq!(Program::from(&*program.to_string()), program);
+        }
+
+        let (optimized, _) = Program::from("+++[->++>+<<]>[-]").optimize();
+        assert_eq!(optimized.to_string(), "+++[->++>+<<]>[-]");
+    }
+
+    #[test]
+    fn constant_output_of_hello_world_and_echo() {
+        let hello = Program::from(
+            "++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.",
+        );
+        assert_eq!(
+            hello.constant_output().as_deref(),
+            Some(&b"Hello World!\n"[..])
+        );
+
+        assert_eq!(Program::from(",[.,]").constant_output(), None);
+        assert_eq!(Program::from("+[-[,]]").constant_output(), None);
+    }
+
+    #[test]
+    fn parse_errors_report_bracket_positions() {
+        let error = Program::new("++[+").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnmatchedOpeningBracket);
+        assert_eq!((error.offset, error.line, error.column), (2, 1, 3));
+
+        let error = Program::new("++]").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnmatchedClosingBracket);
+        assert_eq!((error.offset, error.line, error.column), (2, 1, 3));
+
+        let error = Program::new("+\n[\n  é]]").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnmatchedClosingBracket);
+        assert_eq!((error.offset, error.line, error.column), (9, 3, 5));
+        assert_eq!(
+            error.to_string(),
+            "unmatched closing bracket at line 3, column 5"
+        );
+    }
+
+    #[test]
+    fn display_writes_canonical_source_that_reparses_equivalently() {
+        let program = Program::from("add 3: +++ then loop [> more + <-] done.");
+        assert_eq!(program.to_string(), "+++[>+<-].");
+
+        for source in [
+            "+++++[>+++[>++<-]<-]>>.",
+            "++++[-]>+++[->++<]>.",
+            "-[--->+<]>.",
+        ] {
+            let (optimized, _) = Program::from(source).optimize();
+            let text = optimized.to_string();
+            assert_eq!(Program::from(&*text).optimize().0, optimized);
+            assert_eq!(run_naive(&text), run_naive(source));
+        }
+    }
+
+    #[test]
+    fn expand_macros_inlines_nested_macros() {
+        let macros = HashMap::from([('C', "[-]"), ('T', "+++"), ('N', "TTT")]);
+        let source = expand_macros("NC+>N.<.", &macros).unwrap();
+        assert_eq!(source, "+++++++++[-]+>+++++++++.<.");
+        assert_eq!(run(&Program::from(&*source)).output(), &[9, 1]);
+    }
+
+    #[test]
+    fn expand_macros_rejects_reserved_and_recursive_macros() {
+        let reserved = HashMap::from([('C', "[-]"), ('+', "-")]);
+        assert_eq!(
+            expand_macros("C", &reserved),
+            Err(ExpandError::ReservedName('+'))
+        );
+
+        let recursive = HashMap::from([('A', "+B"), ('B', "-A")]);
+        assert!(matches!(
+            expand_macros("A", &recursive),
+            Err(ExpandError::DepthExceeded('A' | 'B'))
+        ));
+        assert_eq!(expand_macros("+-", &recursive).as_deref(), Ok("+-"));
+    }
+
+    #[test]
+    fn to_c_emits_balanced_control_structures() {
+        fn assert_balanced(code: &str) {
+            let mut depth = 0usize;
+
+            for char in code.chars() {
+                match char {
+                    '{' => depth += 1,
+                    '}' => depth = depth.checked_sub(1).expect("unmatched closing brace"),
+                    _ => {}
+                }
+            }
+
+            assert_eq!(depth, 0, "unmatched opening brace");
+        }
+
+        let code = Program::from(",[>++[>+<-]<-]>>.").to_c(16, 100);
+        assert_balanced(&code);
+        assert!(code.contains("static uint16_t mem[100];"));
+        assert!(code.contains("int main(void) {"));
+        assert_eq!(code.matches("while (mem[p]) {").count(), 2);
+        assert!(code.contains("getchar()"));
+        assert!(code.contains("putchar(mem[p]);"));
+        assert!(code.contains("mem[p] += 2;"));
+        assert!(code.contains("p -= 1;"));
+
+        let (optimized, _) = Program::from("+++[->++<]>[-]").optimize();
+        let code = optimized.to_c(8, 30000);
+        assert_balanced(&code);
+        assert!(!code.contains("while"));
+        assert!(code.contains("mem[p + 1] += mem[p] * 2;"));
+        assert!(code.contains("mem[p] = 0;"));
+    }
+
+    #[test]
+    #[should_panic(expected = "cells of 12 bits")]
+    fn to_c_rejects_unsupported_cell_widths() {
+        Program::from("+").to_c(12, 100);
+    }
+
+    #[test]
+    fn parse_error_offsets_point_at_the_offending_bracket() {
+        for (source, offset) in [("[[]", 0), ("+[]>[<", 4), ("[]]", 2), ("+[-]]]", 4)] {
+            let error = Program::new(source).unwrap_err();
+            assert_eq!(error.offset, offset, "{source:?}");
+            assert!(matches!(source.as_bytes()[error.offset], b'[' | b']'));
+        }
+    }
+
+    #[test]
+    fn movement_followed_by_its_reverse_nets_zero_displacement() {
+        for source in [">>><<>>>>", "<<>>>>>>>>><", "", ">[>>]<<[<]>"] {
+            let program = Program::from(source);
+            let reversed = program.reversed();
+            assert_eq!(reversed.reversed(), program);
+
+            if let Some((offset, _)) = extent(&program.0) {
+                assert_eq!(extent(&reversed.0).unwrap().0, -offset);
+            }
+
+            let round_trip = program.clone().merge(reversed).merge(Program::from("+"));
+            let config = RunConfig {
+                initial_pointer: 4,
+                ..RunConfig::default()
+            };
+            let runner = round_trip
+                .try_run::<16, _, _, u8>(std::iter::empty(), Vec::new(), config)
+                .unwrap();
+
+            let mut expected = [0; 16];
+            expected[4] = 1;
+            assert_eq!(runner.memory(), &expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn to_c_nests_loops_as_while_blocks() {
+        let code = Program::from("+[>,.<-]").to_c(8, 16);
+        let expected = "    size_t p = 0;\n\n    \
+                            mem[p] += 1;\n    \
+                            while (mem[p]) {\n        \
+                                p += 1;\n        \
+                                { int c = getchar(); if (c != EOF) mem[p] = c; }\n        \
+                                putchar(mem[p]);\n        \
+                                p -= 1;\n        \
+                                mem[p] -= 1;\n    \
+                            }\n    \
+                            return 0;\n\
+                        }\n";
+        assert!(code.ends_with(expected), "{code}");
+    }
+
+    #[test]
+    fn to_rust_emits_one_while_per_loop_and_only_needed_mutability() {
+        let code = Program::from("+++[>++[>+<-]<-]>>.[,]<<").to_rust();
+        assert_eq!(code.matches("while mem[p] != T::ZERO {").count(), 3);
+        assert!(code.contains("mut input: impl RunnerInput<T>"));
+        assert!(code.contains("mut output: impl RunnerOutput<T>"));
+        assert!(code.contains("let mut mem = [T::ZERO; N];"));
+        assert!(code.contains("let mut p = 0usize;"));
+        assert!(!code.contains("p -= 2;"));
+
+        let (optimized, _) = Program::from("+++[>++[>+<-]<-]").optimize();
+        assert_eq!(optimized.to_rust().matches("while").count(), 1);
+
+        let code = Program::from("[.]>>").to_rust();
+        assert!(code.contains("_input: impl RunnerInput<T>"));
+        assert!(code.contains("let mem = [T::ZERO; N];"));
+        assert!(code.contains("let p = 0usize;"));
+        assert!(!code.contains("p += 2;"));
+
+        let code = Program::from("").to_rust();
+        assert!(code.contains("_output: impl RunnerOutput<T>"));
+        assert!(code.contains("let _p = 0usize;"));
+    }
+
+    #[test]
+    fn loop_count_counts_nested_and_empty_loops() {
+        assert_eq!(Program::from("[][[]]").loop_count(), 3);
+        assert_eq!(Program::from("+-<>.,").loop_count(), 0);
+    }
+
+    #[test]
+    fn loop_entries_match_loop_count_whether_or_not_optimized() {
+        for (source, entries) in [("+[-][>+<-]", 2), ("++[>+[-]<-]", 3), ("[][[]]", 2)] {
+            let program = Program::from(source);
+            let (optimized, _) = program.clone().optimize();
+            assert_eq!(optimized.loop_count(), program.loop_count());
+
+            for program in [&program, &optimized] {
+                assert_eq!(run(program).loop_entries(), entries, "{source:?}");
+
+                let runner = program
+                    .try_run::<16, _, _, u8>(std::iter::empty(), Vec::new(), RunConfig::default())
+                    .unwrap();
+                assert_eq!(runner.loop_entries(), entries, "{source:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn loop_offsets_are_ignored_when_comparing_programs() {
+        let program = Program::from("++ [>]");
+        assert_eq!(program.to_string(), "++[>]");
+        assert_eq!(Program::from(&*program.to_string()), program);
+        assert_eq!(Program::from("[[-]>] comment"), Program::from("[ [-] > ]"));
+        assert_ne!(Program::from("[>]"), Program::from("[<]"));
+    }
+
+    #[test]
+    fn run_profiled_on_counts_iterations_of_nested_loops() {
+        // The outer loop at offset 2 runs twice, and the inner loop at offset 7 runs three times
+        // for each of those, for six iterations in total.
+        let program = Program::from("++[>+++[>+<-]<-]>>.");
+        let mut runner = Runner::<4, _, _, u8>::new(std::iter::empty(), Vec::new());
+        let counts = program.run_profiled_on(&mut runner);
+
+        assert_eq!(counts, HashMap::from([(2, 2), (7, 6)]));
+        assert_eq!(runner.output(), &[6]);
+
+        let mut runner = Runner::<4, _, _, u8>::new(std::iter::empty(), Vec::new());
+        let counts = Program::from("[+]>++[-]<+[-]").run_profiled_on(&mut runner);
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn increasing_clear_matches_naive_interpreter() {
+        for source in [
+            "+++++[+].",
+            "[+]+.",
+            "--->+++[+]<[+].>.",
+            "+++[>+++++[+]+<-]>.",
+            "++[>+++[>[+]++<-]<-]>>.",
+        ] {
+            let program = Program::from(source);
+            let runner = program.run::<16, _, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+            let (output, memory) = run_naive(source);
+
+            let runner_output: Vec<u8> = runner.output().iter().map(|value| value.0).collect();
+            assert_eq!(runner_output, output, "{source}");
+            assert_eq!(*runner.memory(), memory.map(Wrapping), "{source}");
+            assert_eq!(program.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn increasing_clear_is_detected_inside_other_loops() {
+        let program = Program::from("+[>[+]<-]");
+        let Instruction::Repeat(list, _) = &program.0[1] else {
+            panic!("expected a loop");
+        };
+
+        assert_eq!(
+            list[..],
+            [
+                Instruction::Move(1),
+                Instruction::Clear(1),
+                Instruction::Move(-1),
+                Instruction::Add(-1),
+            ]
+        );
+        assert_ne!(Program::from("[+]"), Program::from("[-]"));
+    }
+
+    #[test]
+    fn clear_steps_non_wrapping_cells_towards_zero() {
+        let run = |source: &str| {
+            Program::from(source)
+                .run::<4, _, _, Saturating<i8>>(std::iter::empty(), Vec::new())
+                .cell_value(0)
+        };
+
+        assert_eq!(run("---[+]"), Saturating(0));
+        assert_eq!(run("+++[-]"), Saturating(0));
+        assert_eq!(run(&format!("{}[+]", "-".repeat(300))), Saturating(0));
+    }
+
+    #[test]
+    fn max_steps_counts_clear_loops_exactly_and_stops_stuck_ones() {
+        /// Finds the smallest step limit which lets a program finish, or `None` if no limit below
+        /// 10000 does.
+        fn steps<T: CellValue>(source: &str) -> Option<u64> {
+            (0..10000).find(|&max| {
+                let config = RunConfig {
+                    max_steps: Some(max),
+                    ..RunConfig::default()
+                };
+
+                Program::from(source)
+                    .try_run::<4, _, _, T>(std::iter::empty(), Vec::new(), config)
+                    .is_ok()
+            })
+        }
+
+        // Wrapping cells go around the end of their range, taking 251 iterations to get from -5 to
+        // zero by decrementing.
+        assert_eq!(steps::<Wrapping<i8>>("-----[-]"), Some(5 + 251 * 2 + 1));
+        assert_eq!(steps::<Wrapping<i8>>("-----[+]"), Some(5 + 5 * 2 + 1));
+        assert_eq!(steps::<Wrapping<i8>>("+++++[+]"), Some(5 + 251 * 2 + 1));
+        assert_eq!(steps::<Wrapping<u8>>("+++++[+]"), Some(5 + 251 * 2 + 1));
+
+        assert_eq!(steps::<Saturating<i8>>("-----[+]"), Some(5 + 5 * 2 + 1));
+        assert_eq!(steps::<Saturating<i8>>("+++++[-]"), Some(5 + 5 * 2 + 1));
+
+        let config = RunConfig {
+            max_steps: Some(1000),
+            ..RunConfig::default()
+        };
+
+        for source in ["-[-]", "+[+]", "-----[-]+"] {
+            let error = Program::from(source)
+                .try_run::<4, _, _, Saturating<i8>>(std::iter::empty(), Vec::new(), config.clone())
+                .unwrap_err();
+            assert_eq!(error, RunError::StepLimitExceeded, "{source}");
+        }
+
+        let error = Program::from("+[+]")
+            .try_run::<4, _, _, Saturating<u8>>(std::iter::empty(), Vec::new(), config)
+            .unwrap_err();
+        assert_eq!(error, RunError::StepLimitExceeded);
+    }
 }
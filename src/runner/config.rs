@@ -0,0 +1,33 @@
+//! Defines options which change how a runner executes programs.
+
+/// Options which change how a runner executes programs.
+///
+/// Limits set here are only enforced by fallible methods, such as `Program::try_run_on`.
+#[derive(Clone, Debug, Default)]
+pub struct RunConfig {
+    /// The maximum number of values a program may write, or `None` if there is no limit.
+    pub max_writes: Option<usize>,
+
+    /// The index of the cell the pointer starts at. Defaults to zero.
+    pub initial_pointer: usize,
+
+    /// The maximum number of loops a program may have nested inside each other, or `None` if there
+    /// is no limit. Since programs are run recursively, deeply nested loops may otherwise overflow
+    /// the stack. Compiled programs are also optimized, displayed, and dropped recursively, so
+    /// untrusted source should be compiled with `Program::new_with_max_depth`, which rejects deep
+    /// nesting before it is ever built.
+    pub max_loop_depth: Option<usize>,
+
+    /// The maximum number of steps a program may take, or `None` if there is no limit. Each `+`,
+    /// `-`, `<`, `>`, `,`, and `.` counts as one step, as does each check of a loop's condition, so
+    /// this stops programs which never finish, like `+[]`. Runs which are folded into a single
+    /// instruction when a program is compiled still count one step per character, and loops which
+    /// are replaced by a single instruction count the steps they would have taken, assuming they
+    /// run as many times as the magnitude of the current cell.
+    pub max_steps: Option<u64>,
+
+    /// Whether the memory tape is circular, which makes the pointer wrap around to the last cell
+    /// when moving left of the first cell, and to the first cell when moving right of the last.
+    /// Defaults to `false`.
+    pub circular_tape: bool,
+}
@@ -0,0 +1,261 @@
+//! Defines a runner whose memory tape grows as programs use it.
+
+use super::{
+    error::RunError,
+    input::RunnerInput,
+    output::{DebuggableRunnerOutput, RunnerOutput},
+    RunnerData, RunnerOutputDebugWrapper, VerbatimDebug, DEBUG_DATA_WIDTH,
+};
+use crate::builder::types::{CellValue, DebuggableCellValue};
+use std::{fmt, marker::PhantomData};
+
+/// A structure which runs brainfuck programs like `Runner`, but stores its memory tape in a `Vec`
+/// which grows whenever the pointer moves past its end. New cells are filled with zero, so programs
+/// can use as much memory as they need without choosing a tape size upfront.
+///
+/// Debugging a dynamic runner shows the same information as debugging a `Runner`.
+pub struct DynRunner<I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue> {
+    memory: Vec<T>,
+    pointer: usize,
+    input: I,
+    output: O,
+}
+
+impl<I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue> DynRunner<I, O, T> {
+    /// Constructs a new dynamic runner given some input, starting with a single cell of memory.
+    pub fn new(input: I, output: O) -> Self {
+        Self {
+            memory: vec![T::ZERO],
+            pointer: 0,
+            input,
+            output,
+        }
+    }
+
+    /// Gets the memory tape of this runner, which includes every cell the pointer has visited.
+    pub fn memory(&self) -> &[T] {
+        &self.memory
+    }
+
+    /// Gets the output this runner is writing to.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Grows the memory tape so that it includes the cell at `index`.
+    fn grow_to(&mut self, index: usize) {
+        if index >= self.memory.len() {
+            self.memory.resize(index + 1, T::ZERO);
+        }
+    }
+
+    #[inline]
+    /// Increments the currently pointed at cell.
+    pub fn inc(&mut self) {
+        self.memory[self.pointer] = self.memory[self.pointer].inc();
+    }
+
+    #[inline]
+    /// Decrements the currently pointed at cell.
+    pub fn dec(&mut self) {
+        self.memory[self.pointer] = self.memory[self.pointer].dec();
+    }
+
+    #[inline]
+    /// Adds a value to the currently pointed at cell, as if it were incremented or decremented
+    /// `amount` times.
+    pub fn add(&mut self, amount: isize) {
+        self.memory[self.pointer] = self.memory[self.pointer].offset(amount);
+    }
+
+    #[inline]
+    /// Runs `[-]` on the currently pointed at cell if `step` is -1, or `[+]` if `step` is 1. This
+    /// sets the cell to zero in constant time if cells wrap, and otherwise runs the loop one step at
+    /// a time.
+    ///
+    /// If the cell saturates before reaching zero, the loop never finishes, so this never returns.
+    pub fn clear(&mut self, step: isize) {
+        match self.memory[self.pointer].cleared(step) {
+            Some(value) => self.memory[self.pointer] = value,
+            None => loop {
+                std::thread::park();
+            },
+        }
+    }
+
+    #[inline]
+    /// Adds the currently pointed at cell's value, multiplied by a factor, to the cell at each
+    /// offset from the pointer, then sets the current cell to zero.
+    pub fn add_mul(&mut self, offsets: &[(isize, isize)]) {
+        let count = self.memory[self.pointer];
+
+        if count == T::ZERO {
+            return;
+        }
+
+        for &(offset, factor) in offsets {
+            let index = self
+                .offset_pointer(offset)
+                .unwrap_or_else(|error| panic!("{error}"));
+
+            self.grow_to(index);
+            self.memory[index] = self.memory[index].add_product(count, factor);
+        }
+
+        self.memory[self.pointer] = T::ZERO;
+    }
+
+    /// Finds the index of the cell `offset` cells away from the pointer. Returns an error if the
+    /// index would be left of the first cell. The memory tape grows to the right, so any index
+    /// past its end is allowed.
+    fn offset_pointer(&self, offset: isize) -> Result<usize, RunError> {
+        self.pointer
+            .checked_add_signed(offset)
+            .ok_or(RunError::PointerUnderflow)
+    }
+
+    #[inline]
+    /// Moves the pointer to the left.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the pointer is already on the first cell.
+    pub fn shl(&mut self) {
+        self.shift(-1);
+    }
+
+    #[inline]
+    /// Moves the pointer to the left, or returns an error if it is already on the first cell.
+    pub fn try_shl(&mut self) -> Result<(), RunError> {
+        self.try_shift(-1)
+    }
+
+    #[inline]
+    /// Moves the pointer to the right, growing the memory tape if needed.
+    pub fn shr(&mut self) {
+        self.pointer += 1;
+        self.grow_to(self.pointer);
+    }
+
+    #[inline]
+    /// Moves the pointer by some offset, growing the memory tape if needed.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the pointer would move left of the first cell.
+    pub fn shift(&mut self, offset: isize) {
+        self.try_shift(offset)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    #[inline]
+    /// Moves the pointer by some offset, growing the memory tape if needed, or returns an error if
+    /// doing so would move it left of the first cell.
+    pub fn try_shift(&mut self, offset: isize) -> Result<(), RunError> {
+        self.pointer = self.offset_pointer(offset)?;
+        self.grow_to(self.pointer);
+        Ok(())
+    }
+
+    #[inline]
+    /// Reads a value from `self.input` into the current cell, or leaves the cell's value as-is if
+    /// there is no input left.
+    pub fn read(&mut self) {
+        if let Some(input) = self.input.read() {
+            self.memory[self.pointer] = input;
+        }
+    }
+
+    #[inline]
+    /// Writes the current cell into `self.output`.
+    pub fn write(&mut self) {
+        self.output.write(self.memory[self.pointer]);
+    }
+
+    #[inline]
+    /// Repeats code while the currently pointed at cell is nonzero.
+    pub fn repeat(&mut self, mut f: impl FnMut(&mut Self)) {
+        while self.memory[self.pointer] != T::ZERO {
+            f(self);
+        }
+    }
+}
+
+impl<
+        I: RunnerInput<T>,
+        O: RunnerOutput<T> + DebuggableRunnerOutput<T>,
+        T: DebuggableCellValue + fmt::Debug,
+    > fmt::Debug for DynRunner<I, O, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let len = self.memory.len();
+        let start = self.pointer.saturating_sub(DEBUG_DATA_WIDTH as usize);
+        let end = len.min(self.pointer + DEBUG_DATA_WIDTH as usize);
+
+        let data = RunnerData {
+            data: &self.memory[start..end],
+            pointer: Some(self.pointer - start),
+            includes_start: start == 0,
+            includes_end: end == len,
+        };
+
+        f.debug_struct("DynRunner")
+            .field("data", &data)
+            .field("input", &VerbatimDebug("..".to_owned()))
+            .field(
+                "output",
+                &RunnerOutputDebugWrapper(&self.output, PhantomData),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+    use std::num::Wrapping;
+
+    #[test]
+    fn memory_grows_to_fit_distant_cells() {
+        let source = format!("+++{}++.{}.", ">".repeat(5000), "<".repeat(5000));
+        let runner =
+            Program::from(&*source).run_dyn::<_, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+
+        assert_eq!(runner.memory().len(), 5001);
+        assert_eq!(runner.memory()[5000], Wrapping(2));
+        assert_eq!(runner.output(), &[Wrapping(2), Wrapping(3)]);
+    }
+
+    #[test]
+    fn moving_left_of_the_first_cell_is_an_error() {
+        let mut runner = DynRunner::<_, _, u8>::new(std::iter::empty(), Vec::new());
+        runner.shr();
+        runner.shl();
+        assert_eq!(runner.try_shl(), Err(RunError::PointerUnderflow));
+        assert_eq!(runner.try_shift(-3), Err(RunError::PointerUnderflow));
+        assert_eq!(runner.memory().len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "pointer moved left of the start of memory")]
+    fn shl_panics_with_a_message_on_the_first_cell() {
+        DynRunner::<_, _, u8>::new(std::iter::empty(), Vec::new()).shl();
+    }
+
+    #[test]
+    fn loops_sweep_far_right_without_a_fixed_bound() {
+        // Carries a counter one cell to the right per iteration until it runs out, then marks the
+        // cell it ends on.
+        let program = Program::from("-[-[->+<]>]+");
+
+        for program in [program.clone(), program.optimize().0] {
+            let runner = program.run_dyn::<_, _, Wrapping<u8>>(std::iter::empty(), Vec::new());
+            assert_eq!(runner.memory().len(), 256);
+            assert_eq!(runner.memory()[255], Wrapping(1));
+            assert!(runner.memory()[..255]
+                .iter()
+                .all(|&cell| cell == Wrapping(0)));
+        }
+    }
+}
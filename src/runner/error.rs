@@ -0,0 +1,36 @@
+//! Defines errors which may occur while running a brainfuck program.
+
+use std::{error::Error, fmt};
+
+/// An error which stopped a brainfuck program from running to completion.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunError {
+    /// The program tried to write more values than allowed by `RunConfig::max_writes`.
+    OutputLimitExceeded,
+
+    /// The program has more nested loops than allowed by `RunConfig::max_loop_depth`.
+    LoopDepthExceeded,
+
+    /// The program took more steps than allowed by `RunConfig::max_steps`.
+    StepLimitExceeded,
+
+    /// The program moved the pointer left of the first cell in memory.
+    PointerUnderflow,
+
+    /// The program moved the pointer right of the last cell in memory.
+    PointerOverflow,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::OutputLimitExceeded => f.write_str("output limit exceeded"),
+            RunError::LoopDepthExceeded => f.write_str("loop depth exceeded"),
+            RunError::StepLimitExceeded => f.write_str("step limit exceeded"),
+            RunError::PointerUnderflow => f.write_str("pointer moved left of the start of memory"),
+            RunError::PointerOverflow => f.write_str("pointer moved right of the end of memory"),
+        }
+    }
+}
+
+impl Error for RunError {}
@@ -0,0 +1,17 @@
+//! Provides a struct implementing `RunnerInput` that returns zero once its input runs out.
+
+use super::RunnerInput;
+use crate::builder::types::CellValue;
+
+/// A struct implementing `RunnerInput` that returns zero once its input runs out, so `.read()`
+/// never returns `None`.
+#[derive(Debug)]
+pub struct ChainZeros<B> {
+    pub(super) base: B,
+}
+
+impl<T: CellValue, B: RunnerInput<T>> RunnerInput<T> for ChainZeros<B> {
+    fn read(&mut self) -> Option<T> {
+        Some(self.base.read().unwrap_or(T::ZERO))
+    }
+}
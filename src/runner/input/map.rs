@@ -0,0 +1,18 @@
+//! Provides a struct implementing `RunnerInput` that maps the values returned by `.read()`.
+
+use super::RunnerInput;
+use std::marker::PhantomData;
+
+/// A struct implementing `RunnerInput` that maps the values returned by `.read()`.
+#[derive(Debug)]
+pub struct MapInput<I, B: RunnerInput<I>, O, T: FnMut(I) -> O> {
+    pub(super) mapper: T,
+    pub(super) _phantom: PhantomData<(I, O)>,
+    pub(super) base: B,
+}
+
+impl<I, B: RunnerInput<I>, O, T: FnMut(I) -> O> RunnerInput<O> for MapInput<I, B, O, T> {
+    fn read(&mut self) -> Option<O> {
+        self.base.read().map(&mut self.mapper)
+    }
+}
@@ -0,0 +1,88 @@
+//! Provides a trait that can be implemented to give input to a runner.
+
+pub mod chain_zeros;
+pub mod map;
+pub mod read;
+
+use std::marker::PhantomData;
+
+use self::{chain_zeros::ChainZeros, map::MapInput};
+
+/// Something which can provide input to a runner.
+///
+/// This is implemented for every `Iterator`, so iterators can be passed to a runner directly.
+pub trait RunnerInput<T> {
+    /// Reads the next value of input, or returns `None` if there is no input left.
+    fn read(&mut self) -> Option<T>;
+
+    /// Maps the values returned by `.read()` through a function.
+    fn map_input<O, F: FnMut(T) -> O>(self, f: F) -> MapInput<T, Self, O, F>
+    where
+        Self: Sized,
+    {
+        MapInput {
+            base: self,
+            mapper: f,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns zero once there is no input left instead of `None`, so that a runner reading past
+    /// the end of input always sets the current cell to zero.
+    fn chain_zeros(self) -> ChainZeros<Self>
+    where
+        Self: Sized,
+    {
+        ChainZeros { base: self }
+    }
+}
+
+impl<T, I: Iterator<Item = T>> RunnerInput<T> for I {
+    /// Reads the next value of input from this iterator.
+    fn read(&mut self) -> Option<T> {
+        self.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+    use std::num::Wrapping;
+
+    /// An input source which counts down from a value to one, then runs out.
+    struct Countdown(u8);
+
+    impl RunnerInput<u8> for Countdown {
+        fn read(&mut self) -> Option<u8> {
+            let value = self.0;
+            self.0 = value.checked_sub(1)?;
+            Some(value).filter(|&value| value != 0)
+        }
+    }
+
+    #[test]
+    fn custom_sources_and_combinators_feed_runners() {
+        let program = Program::from(",.,.,.,.,.");
+
+        let output = program
+            .run::<4, _, _, u8>(Countdown(3), Vec::new())
+            .into_output();
+        assert_eq!(output, [3, 2, 1, 1, 1]);
+
+        let output = program
+            .run::<4, _, _, u8>(Countdown(3).chain_zeros(), Vec::new())
+            .into_output();
+        assert_eq!(output, [3, 2, 1, 0, 0]);
+
+        let output = program
+            .run::<4, _, _, Wrapping<u8>>(
+                Countdown(2)
+                    .map_input(|value| Wrapping(value * 10))
+                    .chain_zeros(),
+                Vec::new(),
+            )
+            .into_output();
+        assert_eq!(output, [20, 10, 0, 0, 0].map(Wrapping));
+    }
+}
@@ -0,0 +1,41 @@
+//! Provides a struct implementing `RunnerInput` that reads bytes from an `io::Read` handle.
+
+use super::RunnerInput;
+use std::{io::Read, num::Wrapping};
+
+/// A struct implementing `RunnerInput` that reads bytes one at a time from an `io::Read` handle,
+/// such as a file or stdin. Input ends when the handle reaches its end or returns an error.
+#[derive(Debug)]
+pub struct ReadInput<R> {
+    reader: R,
+}
+
+impl<R: Read> ReadInput<R> {
+    /// Creates a runner input which reads bytes from `reader`. Since bytes are read one at a time,
+    /// wrapping `reader` in a `BufReader` is recommended for files and sockets.
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Reads a single byte from the underlying reader.
+    fn read_byte(&mut self) -> Option<u8> {
+        let mut byte = [0];
+
+        match self.reader.read_exact(&mut byte) {
+            Ok(()) => Some(byte[0]),
+            Err(_) => None,
+        }
+    }
+}
+
+impl<R: Read> RunnerInput<u8> for ReadInput<R> {
+    fn read(&mut self) -> Option<u8> {
+        self.read_byte()
+    }
+}
+
+impl<R: Read> RunnerInput<Wrapping<u8>> for ReadInput<R> {
+    fn read(&mut self) -> Option<Wrapping<u8>> {
+        self.read_byte().map(Wrapping)
+    }
+}
@@ -0,0 +1,312 @@
+//! Defines a runner which can step backwards through a program by recording how to undo each step.
+
+use super::{input::RunnerInput, RunnerData, DEBUG_DATA_WIDTH};
+use crate::{builder::types::CellValue, program::Program};
+use std::fmt;
+
+/// A single instruction in a program whose loops have been flattened into jumps, which lets it be
+/// run one instruction at a time.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Step {
+    Add(isize),
+    Move(isize),
+    Read,
+    Write,
+    /// Runs `[-]` or `[+]`, depending on whether the loop adds -1 or 1.
+    Clear(isize),
+    /// Jumps to the step after the given index if the current cell is zero.
+    JumpIfZero(usize),
+    /// Jumps to the step after the given index if the current cell is nonzero.
+    JumpIfNonzero(usize),
+}
+
+/// The information needed to reverse a single step.
+#[derive(Clone, Copy, Debug)]
+enum Undo<T> {
+    /// The step changed the current cell, which previously held this value.
+    Cell(T),
+    /// The step moved the pointer, which was previously at this index.
+    Pointer(usize),
+    /// The step read a value into the current cell, which previously held this value. If the
+    /// value was taken from the input, the input is rewound by one.
+    Read(T, bool),
+    /// The step wrote a value, which should be removed from the end of the output.
+    Write,
+    /// The step only moved the program counter.
+    Jump,
+}
+
+/// A runner which executes a program one instruction at a time and records enough information to
+/// undo each instruction, allowing a debugger to step backwards as well as forwards.
+///
+/// Values read from the input are kept, so stepping back over a read and then forwards again reads
+/// the same value. Values written are collected into a `Vec`.
+///
+/// Debugging a journaling runner shows the part of memory near the pointer, like a `Runner`, and
+/// the number of steps which can be undone rather than the whole journal.
+pub struct JournalingRunner<const N: usize, I: RunnerInput<T>, T: CellValue> {
+    steps: Vec<Step>,
+    counter: usize,
+    memory: [T; N],
+    pointer: usize,
+    input: I,
+    input_read: Vec<T>,
+    input_position: usize,
+    output: Vec<T>,
+    journal: Vec<(usize, Undo<T>)>,
+}
+
+impl<const N: usize, I: RunnerInput<T>, T: CellValue> JournalingRunner<N, I, T> {
+    /// Constructs a new runner which will run `program` on some input.
+    pub fn new(program: &Program, input: I) -> Self {
+        if N == 0 {
+            panic!("cannot create a runner of size zero");
+        }
+
+        Self {
+            steps: program.flatten(),
+            counter: 0,
+            memory: [T::ZERO; N],
+            pointer: 0,
+            input,
+            input_read: Vec::new(),
+            input_position: 0,
+            output: Vec::new(),
+            journal: Vec::new(),
+        }
+    }
+
+    /// Gets the memory tape of this runner.
+    pub fn memory(&self) -> &[T; N] {
+        &self.memory
+    }
+
+    /// Gets the index of the currently pointed at cell.
+    pub fn pointer(&self) -> usize {
+        self.pointer
+    }
+
+    /// Gets the values written so far.
+    pub fn output(&self) -> &[T] {
+        &self.output
+    }
+
+    /// Gets the number of steps which have been run and not undone.
+    pub fn steps_taken(&self) -> usize {
+        self.journal.len()
+    }
+
+    /// Checks whether every instruction of the program has been run.
+    pub fn is_finished(&self) -> bool {
+        self.counter >= self.steps.len()
+    }
+
+    /// Runs a single instruction, recording how to undo it. Returns `false` without doing anything
+    /// if the program has already finished.
+    pub fn step(&mut self) -> bool {
+        let Some(&step) = self.steps.get(self.counter) else {
+            return false;
+        };
+
+        let counter = self.counter;
+        let current = self.memory[self.pointer];
+        self.counter += 1;
+
+        let undo = match step {
+            Step::Add(amount) => {
+                self.memory[self.pointer] = current.offset(amount);
+                Undo::Cell(current)
+            }
+            Step::Move(offset) => {
+                let previous = self.pointer;
+                self.pointer = self
+                    .pointer
+                    .checked_add_signed(offset)
+                    .filter(|&pointer| pointer < N)
+                    .expect("pointer moved outside of memory");
+                Undo::Pointer(previous)
+            }
+            Step::Read => {
+                let value = match self.input_read.get(self.input_position) {
+                    Some(&value) => Some(value),
+                    None => self
+                        .input
+                        .read()
+                        .inspect(|&value| self.input_read.push(value)),
+                };
+
+                if let Some(value) = value {
+                    self.memory[self.pointer] = value;
+                    self.input_position += 1;
+                }
+
+                Undo::Read(current, value.is_some())
+            }
+            Step::Write => {
+                self.output.push(current);
+                Undo::Write
+            }
+            Step::Clear(step) => {
+                match current.cleared(step) {
+                    Some(value) => self.memory[self.pointer] = value,
+                    None => {
+                        // The loop never finishes, so run one iteration and stay on this step.
+                        self.memory[self.pointer] = current.offset(step);
+                        self.counter = counter;
+                    }
+                }
+
+                Undo::Cell(current)
+            }
+            Step::JumpIfZero(target) => {
+                if current == T::ZERO {
+                    self.counter = target + 1;
+                }
+
+                Undo::Jump
+            }
+            Step::JumpIfNonzero(target) => {
+                if current != T::ZERO {
+                    self.counter = target + 1;
+                }
+
+                Undo::Jump
+            }
+        };
+
+        self.journal.push((counter, undo));
+        true
+    }
+
+    /// Reverses the most recently run instruction, restoring the memory, pointer, input, and
+    /// output to exactly how they were before it ran. Returns `false` without doing anything if no
+    /// instructions have been run.
+    pub fn step_back(&mut self) -> bool {
+        let Some((counter, undo)) = self.journal.pop() else {
+            return false;
+        };
+
+        self.counter = counter;
+
+        match undo {
+            Undo::Cell(value) => self.memory[self.pointer] = value,
+            Undo::Pointer(pointer) => self.pointer = pointer,
+            Undo::Read(value, consumed) => {
+                self.memory[self.pointer] = value;
+
+                if consumed {
+                    self.input_position -= 1;
+                }
+            }
+            Undo::Write => {
+                self.output.pop();
+            }
+            Undo::Jump => {}
+        }
+
+        true
+    }
+
+    /// Runs instructions until the program finishes.
+    pub fn run(&mut self) {
+        while self.step() {}
+    }
+}
+
+impl<const N: usize, I: RunnerInput<T>, T: CellValue + fmt::Debug> fmt::Debug
+    for JournalingRunner<N, I, T>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = 0i32.max(self.pointer as i32 - DEBUG_DATA_WIDTH) as usize;
+        let end = (N as i32).min(self.pointer as i32 + DEBUG_DATA_WIDTH) as usize;
+
+        let data = RunnerData {
+            data: &self.memory[start..end],
+            pointer: Some(self.pointer - start),
+            includes_start: start == 0,
+            includes_end: end == N,
+        };
+
+        let output = RunnerData {
+            data: &self.output[..],
+            pointer: None,
+            includes_start: true,
+            includes_end: true,
+        };
+
+        f.debug_struct("JournalingRunner")
+            .field("data", &data)
+            .field("output", &output)
+            .field("steps_taken", &self.journal.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::Wrapping;
+
+    #[test]
+    fn step_back_restores_every_previous_state() {
+        let program = Program::from(",>++[<+>-]<.");
+        let mut runner =
+            JournalingRunner::<4, _, Wrapping<u8>>::new(&program, [Wrapping(5)].into_iter());
+        let mut states = Vec::new();
+
+        loop {
+            states.push((*runner.memory(), runner.pointer(), runner.output().to_vec()));
+
+            if !runner.step() {
+                break;
+            }
+        }
+
+        assert_eq!(runner.output(), [Wrapping(7)]);
+
+        while let Some((memory, pointer, output)) = states.pop() {
+            assert_eq!(runner.memory(), &memory);
+            assert_eq!(runner.pointer(), pointer);
+            assert_eq!(runner.output(), output);
+            runner.step_back();
+        }
+
+        assert!(!runner.step_back());
+        runner.run();
+        assert_eq!(runner.output(), [Wrapping(7)]);
+    }
+
+    #[test]
+    fn debug_summarizes_memory_and_journal() {
+        let program = Program::from("+>+.");
+        let mut runner = JournalingRunner::<1000, _, u8>::new(&program, std::iter::empty());
+        runner.run();
+
+        assert_eq!(
+            format!("{runner:?}"),
+            "JournalingRunner { data: 1 <1> 0 0 0 0 0 0 0 .., output: 1, steps_taken: 4 }"
+        );
+    }
+
+    #[test]
+    fn clear_which_never_finishes_keeps_running() {
+        use std::num::Saturating;
+
+        let program = Program::from("--[-]+");
+        let mut runner =
+            JournalingRunner::<4, _, Saturating<i8>>::new(&program, std::iter::empty());
+
+        for _ in 0..200 {
+            assert!(runner.step());
+        }
+
+        assert!(!runner.is_finished());
+        assert_eq!(runner.memory()[0], Saturating(i8::MIN));
+
+        while runner.steps_taken() > 1 {
+            runner.step_back();
+        }
+
+        assert_eq!(runner.memory()[0], Saturating(-2));
+    }
+}
@@ -1,5 +1,10 @@
 //! Defines a runner for brainfuck programs.
 
+pub mod config;
+pub mod dynamic;
+pub mod error;
+pub mod input;
+pub mod journal;
 pub mod output;
 
 use crate::builder::types::{CellValue, DebuggableCellValue};
@@ -8,7 +13,12 @@ use std::{
     marker::PhantomData,
 };
 
-use self::output::{DebuggableRunnerOutput, RunnerOutput};
+use self::{
+    config::RunConfig,
+    error::RunError,
+    input::RunnerInput,
+    output::{DebuggableRunnerOutput, RunnerOutput},
+};
 
 /// A structure which can quickly run brainfuck programs.
 ///
@@ -21,28 +31,102 @@ use self::output::{DebuggableRunnerOutput, RunnerOutput};
 /// The `N` const parameter is the size of the memory array, and the `T` type generic is the type of
 /// value stored inside. All integer values may be used, along with their `Wrapping` and
 /// `Saturating` variants.
-pub struct Runner<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue> {
+pub struct Runner<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue> {
     memory: [T; N],
     pointer: usize,
     input: I,
     output: O,
+    config: RunConfig,
+    writes: usize,
+    steps: u64,
+    loop_entries: u64,
+    signed_display: bool,
 }
 
-impl<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue> Runner<N, I, O, T> {
+impl<const N: usize, I: RunnerInput<T>, O: RunnerOutput<T>, T: CellValue> Runner<N, I, O, T> {
     /// Constructs a new runner given some input.
     pub fn new(input: I, output: O) -> Self {
+        Self::with_config(input, output, RunConfig::default())
+    }
+
+    /// Constructs a new runner given some input and options for how programs should be run.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `config.initial_pointer` is outside of the memory tape.
+    pub fn with_config(input: I, output: O, config: RunConfig) -> Self {
         if N == 0 {
             panic!("cannot create a runner of size zero");
         }
 
+        if config.initial_pointer >= N {
+            panic!("cannot start a runner with its pointer outside of memory");
+        }
+
         Self {
             memory: [T::ZERO; N],
-            pointer: 0,
+            pointer: config.initial_pointer,
             input,
             output,
+            config,
+            writes: 0,
+            steps: 0,
+            loop_entries: 0,
+            signed_display: false,
         }
     }
 
+    /// Gets the options this runner was created with.
+    pub fn config(&self) -> &RunConfig {
+        &self.config
+    }
+
+    /// Sets whether debugging this runner shows the values in its memory tape as signed numbers,
+    /// reinterpreting the bits of unsigned cells as two's complement. This is useful when unsigned
+    /// cells like `Wrapping<u8>` are used to store signed values.
+    pub fn set_signed_display(&mut self, signed_display: bool) {
+        self.signed_display = signed_display;
+    }
+
+    /// Gets the number of times this runner has reached the start of a loop through `.repeat()`
+    /// or `.try_repeat()`, including loops whose bodies were skipped. Loops replaced by `.clear()`
+    /// or `.add_mul()` are counted too, so optimizing a program doesn't change this count. Compared
+    /// against `Program::loop_count`, this gives a rough idea of how far a program has progressed.
+    pub fn loop_entries(&self) -> u64 {
+        self.loop_entries
+    }
+
+    /// Gets the memory tape of this runner.
+    pub fn memory(&self) -> &[T; N] {
+        &self.memory
+    }
+
+    /// Gets the value of the cell at `location`, which may come from `Cell::location` to check the
+    /// value a builder's cell ended up with.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if `location` is not within `0..N`.
+    pub fn cell_value(&self, location: usize) -> T {
+        self.memory[location]
+    }
+
+    /// Gets mutable access to the memory tape of this runner, which can be used to preload values
+    /// before running a program.
+    pub fn memory_mut(&mut self) -> &mut [T; N] {
+        &mut self.memory
+    }
+
+    /// Gets the output this runner is writing to.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Consumes this runner and returns the output it was writing to.
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
     #[inline]
     /// Increments the currently pointed at cell.
     pub fn inc(&mut self) {
@@ -55,16 +139,152 @@ impl<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue> Ru
         self.memory[self.pointer] = self.memory[self.pointer].dec();
     }
 
+    #[inline]
+    /// Adds a value to the currently pointed at cell, as if it were incremented or decremented
+    /// `amount` times.
+    pub fn add(&mut self, amount: isize) {
+        self.memory[self.pointer] = self.memory[self.pointer].offset(amount);
+    }
+
+    #[inline]
+    /// Runs `[-]` on the currently pointed at cell if `step` is -1, or `[+]` if `step` is 1. This
+    /// sets the cell to zero in constant time if cells wrap, and otherwise runs the loop one step at
+    /// a time. Like `.repeat()`, this counts as reaching the start of a loop.
+    ///
+    /// If the cell saturates before reaching zero, the loop never finishes, so this never returns.
+    /// Use `.try_clear()` with a `max_steps` limit to stop with an error instead.
+    pub fn clear(&mut self, step: isize) {
+        self.loop_entries += 1;
+
+        match self.memory[self.pointer].cleared(step) {
+            Some(value) => self.memory[self.pointer] = value,
+            None => loop {
+                std::thread::park();
+            },
+        }
+    }
+
+    #[inline]
+    /// Adds the currently pointed at cell's value, multiplied by a factor, to the cell at each
+    /// offset from the pointer, then sets the current cell to zero. This does the same thing as a
+    /// loop like `[->++>+<<]`, but in constant time. Like `.repeat()`, this counts as reaching the
+    /// start of a loop.
+    pub fn add_mul(&mut self, offsets: &[(isize, isize)]) {
+        self.loop_entries += 1;
+
+        let count = self.memory[self.pointer];
+
+        if count == T::ZERO {
+            return;
+        }
+
+        for &(offset, factor) in offsets {
+            let index = self
+                .offset_pointer(offset)
+                .unwrap_or_else(|error| panic!("{error}"));
+
+            self.memory[index] = self.memory[index].add_product(count, factor);
+        }
+
+        self.memory[self.pointer] = T::ZERO;
+    }
+
+    #[inline]
+    /// Does the same thing as `.clear()`, but first counts the steps the `[-]` or `[+]` loop would
+    /// have taken towards the configured `max_steps`, returning an error if that would exceed it. A
+    /// loop which never finishes because the cell saturates always exceeds the limit.
+    pub fn try_clear(&mut self, step: isize) -> Result<(), RunError> {
+        self.try_steps(self.loop_steps(step, 1))?;
+        self.clear(step);
+        Ok(())
+    }
+
+    #[inline]
+    /// Does the same thing as `.add_mul()`, but returns an error instead of panicking if any of the
+    /// cells it would change are outside of memory. The steps the loop it replaces would have taken
+    /// are counted towards the configured `max_steps` first, returning an error if that would
+    /// exceed it.
+    pub fn try_add_mul(&mut self, offsets: &[(isize, isize)]) -> Result<(), RunError> {
+        let mut pointer = 0;
+        let mut body_steps = 1;
+
+        for &(offset, factor) in offsets {
+            body_steps += (offset - pointer).unsigned_abs() as u64 + factor.unsigned_abs() as u64;
+            pointer = offset;
+        }
+
+        body_steps += pointer.unsigned_abs() as u64;
+        self.try_steps(self.loop_steps(-1, body_steps))?;
+
+        if self.memory[self.pointer] != T::ZERO {
+            for &(offset, _) in offsets {
+                self.offset_pointer(offset)?;
+            }
+        }
+
+        self.add_mul(offsets);
+        Ok(())
+    }
+
+    /// Finds the index of the cell `offset` cells away from the pointer, wrapping around the ends
+    /// of memory if the tape is circular. Returns an error if the index would be outside of memory.
+    fn offset_pointer(&self, offset: isize) -> Result<usize, RunError> {
+        if self.config.circular_tape {
+            let len = N as isize;
+            return Ok((self.pointer as isize + offset % len).rem_euclid(len) as usize);
+        }
+
+        match self.pointer.checked_add_signed(offset) {
+            Some(index) if index < N => Ok(index),
+            Some(_) => Err(RunError::PointerOverflow),
+            None if offset < 0 => Err(RunError::PointerUnderflow),
+            None => Err(RunError::PointerOverflow),
+        }
+    }
+
     #[inline]
     /// Moves the pointer to the left.
     pub fn shl(&mut self) {
-        self.pointer -= 1;
+        self.shift(-1);
+    }
+
+    #[inline]
+    /// Moves the pointer to the left, or returns an error if it is already on the first cell.
+    pub fn try_shl(&mut self) -> Result<(), RunError> {
+        self.try_shift(-1)
     }
 
     #[inline]
     /// Moves the pointer to the right.
     pub fn shr(&mut self) {
-        self.pointer += 1;
+        self.shift(1);
+    }
+
+    #[inline]
+    /// Moves the pointer to the right, or returns an error if it is already on the last cell.
+    pub fn try_shr(&mut self) -> Result<(), RunError> {
+        self.try_shift(1)
+    }
+
+    #[inline]
+    /// Moves the pointer by some offset, where negative offsets move left and positive offsets
+    /// move right.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the pointer would move outside of memory and the tape is not circular.
+    pub fn shift(&mut self, offset: isize) {
+        self.pointer = self
+            .offset_pointer(offset)
+            .unwrap_or_else(|error| panic!("{error}"));
+    }
+
+    #[inline]
+    /// Moves the pointer by some offset, or returns an error if doing so would move it outside of
+    /// memory.
+    pub fn try_shift(&mut self, offset: isize) -> Result<(), RunError> {
+        self.pointer = self.offset_pointer(offset)?;
+        Ok(())
     }
 
     #[inline]
@@ -75,11 +295,11 @@ impl<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue> Ru
     /// 1. In a program, you can set the cell's value before reading input. For example, `[-],` will
     ///    read a byte if there is one and set the cell to zero otherwise.
     ///
-    /// 2. Add an extension to the iterator to ensures there are always cells to read. For example,
-    ///    `input.chain([0].iter().cycle())` will ensure that all cells are set to zero once there
-    ///    is no more memory left to read.
+    /// 2. Add an extension to the input to ensures there are always cells to read. For example,
+    ///    `input.chain_zeros()` will ensure that all cells are set to zero once there is no more
+    ///    memory left to read.
     pub fn read(&mut self) {
-        if let Some(input) = self.input.next() {
+        if let Some(input) = self.input.read() {
             self.memory[self.pointer] = input;
         }
     }
@@ -88,25 +308,96 @@ impl<const N: usize, I: Iterator<Item = T>, O: RunnerOutput<T>, T: CellValue> Ru
     /// Writes the current cell into `self.output`.
     pub fn write(&mut self) {
         self.output.write(self.memory[self.pointer]);
+        self.writes += 1;
+    }
+
+    #[inline]
+    /// Writes the current cell into `self.output`, or returns an error if doing so would exceed
+    /// the configured `max_writes`.
+    pub fn try_write(&mut self) -> Result<(), RunError> {
+        if self.config.max_writes.is_some_and(|max| self.writes >= max) {
+            return Err(RunError::OutputLimitExceeded);
+        }
+
+        self.write();
+        Ok(())
+    }
+
+    #[inline]
+    /// Counts a step towards the configured `max_steps`, or returns an error if doing so would
+    /// exceed it.
+    pub fn try_step(&mut self) -> Result<(), RunError> {
+        self.try_steps(1)
+    }
+
+    #[inline]
+    /// Counts several steps towards the configured `max_steps`, or returns an error if doing so
+    /// would exceed it.
+    pub fn try_steps(&mut self, count: u64) -> Result<(), RunError> {
+        let steps = self.steps.saturating_add(count);
+
+        if self.config.max_steps.is_some_and(|max| steps > max) {
+            return Err(RunError::StepLimitExceeded);
+        }
+
+        self.steps = steps;
+        Ok(())
+    }
+
+    /// Counts the steps a loop which adds `step` to the current cell until it is zero would take,
+    /// if each of its iterations takes `body_steps` steps. Every iteration also checks the loop's
+    /// condition, as does the final check which exits it. A loop which never finishes because the
+    /// cell saturates first takes `u64::MAX` steps, which is more than any `max_steps` allows.
+    fn loop_steps(&self, step: isize, body_steps: u64) -> u64 {
+        let Some(iterations) = self.memory[self.pointer].steps_to_zero(step) else {
+            return u64::MAX;
+        };
+
+        u64::try_from(iterations)
+            .unwrap_or(u64::MAX)
+            .saturating_mul(body_steps + 1)
+            .saturating_add(1)
     }
 
     #[inline]
     /// Repeats code while the currently pointed at cell is nonzero.
     pub fn repeat(&mut self, mut f: impl FnMut(&mut Self)) {
+        self.loop_entries += 1;
+
         while self.memory[self.pointer] != T::ZERO {
             f(self);
         }
     }
+
+    #[inline]
+    /// Repeats fallible code while the currently pointed at cell is nonzero, stopping at the first
+    /// error. Each check of the current cell counts as a step towards the configured `max_steps`.
+    pub fn try_repeat(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<(), RunError>,
+    ) -> Result<(), RunError> {
+        self.loop_entries += 1;
+
+        loop {
+            self.try_step()?;
+
+            if self.memory[self.pointer] == T::ZERO {
+                return Ok(());
+            }
+
+            f(self)?;
+        }
+    }
 }
 
-struct RunnerData<'a, T: CellValue> {
+struct RunnerData<'a, T> {
     data: &'a [T],
     pointer: Option<usize>,
     includes_start: bool,
     includes_end: bool,
 }
 
-impl<T: CellValue + fmt::Debug> fmt::Debug for RunnerData<'_, T> {
+impl<T: fmt::Debug> fmt::Debug for RunnerData<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if !self.includes_start {
             f.write_str(".. ")?;
@@ -174,43 +465,186 @@ const DEBUG_DATA_WIDTH: i32 = 8;
 
 impl<
         const N: usize,
-        I: Iterator<Item = T>,
+        I: RunnerInput<T>,
         O: RunnerOutput<T> + DebuggableRunnerOutput<T>,
         T: DebuggableCellValue + fmt::Debug,
     > fmt::Debug for Runner<N, I, O, T>
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let data = {
-            let start = 0i32.max(self.pointer as i32 - DEBUG_DATA_WIDTH) as usize;
-            let end = (N as i32).min(self.pointer as i32 + DEBUG_DATA_WIDTH) as usize;
-            let pointer = self.pointer - 0i32.max(self.pointer as i32 - DEBUG_DATA_WIDTH) as usize;
-
-            RunnerData {
-                data: &self.memory[start..end],
-                pointer: Some(pointer),
-                includes_start: start == 0,
-                includes_end: end == N,
-            }
-        };
+        let start = 0i32.max(self.pointer as i32 - DEBUG_DATA_WIDTH) as usize;
+        let end = (N as i32).min(self.pointer as i32 + DEBUG_DATA_WIDTH) as usize;
+
+        if self.signed_display {
+            let signed: Vec<_> = self.memory[start..end]
+                .iter()
+                .map(|value| value.into_signed())
+                .collect();
 
-        if f.sign_minus() {
-            f.debug_struct("Runner")
-                .field("data", &data)
-                .field("input", &VerbatimDebug("..".to_owned()))
-                .field(
-                    "output",
-                    &RunnerOutputDebugWrapper(&self.output, PhantomData),
-                )
-                .finish()
+            self.fmt_with_data(f, &signed, start, end)
         } else {
-            f.debug_struct("Runner")
-                .field("data", &data)
-                .field("input", &VerbatimDebug("..".to_owned()))
-                .field(
-                    "output",
-                    &RunnerOutputDebugWrapper(&self.output, PhantomData),
-                )
-                .finish()
+            self.fmt_with_data(f, &self.memory[start..end], start, end)
         }
     }
 }
+
+impl<
+        const N: usize,
+        I: RunnerInput<T>,
+        O: RunnerOutput<T> + DebuggableRunnerOutput<T>,
+        T: DebuggableCellValue + fmt::Debug,
+    > Runner<N, I, O, T>
+{
+    /// Debugs this runner, showing `data` as the visible part of its memory tape, which spans from
+    /// `start` to `end`.
+    fn fmt_with_data<U: fmt::Debug>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        data: &[U],
+        start: usize,
+        end: usize,
+    ) -> fmt::Result {
+        let data = RunnerData {
+            data,
+            pointer: Some(self.pointer - start),
+            includes_start: start == 0,
+            includes_end: end == N,
+        };
+
+        f.debug_struct("Runner")
+            .field("data", &data)
+            .field("input", &VerbatimDebug("..".to_owned()))
+            .field(
+                "output",
+                &RunnerOutputDebugWrapper(&self.output, PhantomData),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+
+    #[test]
+    fn initial_pointer_starts_at_given_cell() {
+        let config = RunConfig {
+            initial_pointer: 3,
+            ..RunConfig::default()
+        };
+        let mut runner = Runner::<8, _, _, u8>::with_config(std::iter::empty(), Vec::new(), config);
+        runner.memory_mut()[..4].copy_from_slice(&[10, 20, 30, 40]);
+
+        Program::from(".<.").run_on(&mut runner);
+        assert_eq!(runner.output(), &[40, 30]);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of memory")]
+    fn initial_pointer_must_be_in_memory() {
+        let config = RunConfig {
+            initial_pointer: 8,
+            ..RunConfig::default()
+        };
+        Runner::<8, _, _, u8>::with_config(std::iter::empty(), Vec::new(), config);
+    }
+
+    #[test]
+    fn circular_tape_wraps_pointer_at_both_ends() {
+        let config = RunConfig {
+            circular_tape: true,
+            ..RunConfig::default()
+        };
+        let mut runner =
+            Runner::<4, _, _, u8>::with_config([7].into_iter(), Vec::new(), config.clone());
+
+        Program::from("<+++,>>.<<<<<<.")
+            .try_run_on(&mut runner)
+            .unwrap();
+
+        assert_eq!(runner.memory(), &[0, 0, 0, 7]);
+        assert_eq!(runner.output(), &[0, 7]);
+
+        let mut runner = Runner::<4, _, _, u8>::with_config(std::iter::empty(), Vec::new(), config);
+        Program::from(">>>>+>>>>>++").run_on(&mut runner);
+        assert_eq!(runner.memory(), &[1, 2, 0, 0]);
+    }
+
+    #[test]
+    fn step_limit_counts_loop_iterations() {
+        let limited = |max_steps| {
+            let config = RunConfig {
+                max_steps: Some(max_steps),
+                ..RunConfig::default()
+            };
+
+            Runner::<4, _, _, u8>::with_config(std::iter::empty(), Vec::new(), config)
+        };
+
+        let mut runner = limited(10_000);
+        let error = Program::from("+[]").try_run_on(&mut runner).unwrap_err();
+        assert_eq!(error, RunError::StepLimitExceeded);
+
+        // Four `+`, then four iterations of a condition check and four instructions, then a final
+        // condition check.
+        let program = Program::from("++++[>+<-]");
+        assert!(program.try_run_on(&mut limited(25)).is_ok());
+        assert!(program.try_run_on(&mut limited(24)).is_err());
+    }
+
+    #[test]
+    fn moving_outside_memory_is_an_error_in_both_directions() {
+        let mut runner = Runner::<2, _, _, u8>::new(std::iter::empty(), Vec::new());
+        assert_eq!(runner.try_shl(), Err(RunError::PointerUnderflow));
+        assert_eq!(runner.try_shr(), Ok(()));
+        assert_eq!(runner.try_shr(), Err(RunError::PointerOverflow));
+        assert_eq!(runner.try_shift(-2), Err(RunError::PointerUnderflow));
+        assert_eq!(runner.try_shift(-1), Ok(()));
+
+        let program = Program::from("+[>+]");
+        let error = program.try_run_on(&mut runner).unwrap_err();
+        assert_eq!(error, RunError::PointerOverflow);
+        assert_eq!(
+            error.to_string(),
+            "pointer moved right of the end of memory"
+        );
+    }
+
+    #[test]
+    fn signed_display_shows_cells_as_twos_complement() {
+        use std::num::Wrapping;
+
+        let mut runner =
+            Runner::<4, _, _, Wrapping<u8>>::new(std::iter::empty(), Vec::<Wrapping<u8>>::new());
+        runner.memory_mut()[..2].copy_from_slice(&[Wrapping(200), Wrapping(5)]);
+        assert!(format!("{runner:?}").contains("<200> 5 0 0"));
+
+        runner.set_signed_display(true);
+        assert!(format!("{runner:?}").contains("<-56> 5 0 0"));
+
+        runner.set_signed_display(false);
+        assert!(format!("{runner:?}").contains("<200> 5 0 0"));
+    }
+
+    #[test]
+    fn cell_value_reads_builder_cells_after_running() {
+        use crate::builder::core::Builder;
+
+        let builder = Builder::<8, u8>::new();
+        let _padding = builder.cell(1);
+        let mut accumulator = builder.cell(40);
+        accumulator.inc();
+        accumulator.inc();
+
+        // Cells are zeroed when dropped, so the accumulator must outlive the run.
+        let runner = builder.run(std::iter::empty(), Vec::new()).unwrap();
+        assert_eq!(accumulator.location(), 1);
+        assert_eq!(runner.cell_value(accumulator.location()), 42);
+    }
+
+    #[test]
+    #[should_panic]
+    fn cell_value_panics_outside_of_memory() {
+        Runner::<8, _, _, u8>::new(std::iter::empty(), Vec::new()).cell_value(8);
+    }
+}
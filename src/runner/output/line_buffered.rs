@@ -0,0 +1,139 @@
+//! Provides a struct implementing `RunnerOutput` that writes to an inner writer one line at a
+//! time.
+
+use super::{DebuggableRunnerOutput, RunnerOutput};
+use std::{io::Write, num::Wrapping};
+
+/// A struct implementing `RunnerOutput` that buffers values and only passes them to an inner writer
+/// once a newline is written or the struct is dropped.
+#[derive(Debug)]
+pub struct LineBufferedOutput<W: Write> {
+    inner: W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> LineBufferedOutput<W> {
+    /// Creates a new `LineBufferedOutput` which writes to `inner`.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Gets the writer this struct is wrapping.
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writes and flushes everything which has been buffered.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.write_all(&self.buffer)?;
+        self.buffer.clear();
+        self.inner.flush()
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+
+        if bytes.contains(&b'\n') {
+            self.flush().unwrap();
+        }
+    }
+}
+
+impl<W: Write> Drop for LineBufferedOutput<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+macro_rules! direct_runner_output_impl {
+    ($($x:ty)+) => {
+        $(
+            impl<W: Write> RunnerOutput<$x> for LineBufferedOutput<W> {
+                fn write(&mut self, value: $x) {
+                    self.push(&[value][..]);
+                }
+            }
+
+            impl<W: Write> RunnerOutput<Wrapping<$x>> for LineBufferedOutput<W> {
+                fn write(&mut self, value: Wrapping<$x>) {
+                    self.push(&[value.0][..]);
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! runner_output_impl {
+    ($($x:ty)+) => {
+        $(
+            impl<W: Write> RunnerOutput<$x> for LineBufferedOutput<W> {
+                fn write(&mut self, value: $x) {
+                    self.push(char::from_u32(value as u32)
+                        .unwrap()
+                        .encode_utf8(&mut [0; 4])
+                        .as_bytes());
+                }
+            }
+
+            impl<W: Write> RunnerOutput<Wrapping<$x>> for LineBufferedOutput<W> {
+                fn write(&mut self, value: Wrapping<$x>) {
+                    self.push(char::from_u32(value.0 as u32)
+                        .unwrap()
+                        .encode_utf8(&mut [0; 4])
+                        .as_bytes());
+                }
+            }
+        )+
+    }
+}
+
+direct_runner_output_impl! { u8 }
+runner_output_impl! { u16 u32 u64 u128 }
+
+impl<T, W: Write> DebuggableRunnerOutput<T> for LineBufferedOutput<W> {
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("(line buffered)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer which records the bytes it had received each time it was flushed.
+    #[derive(Default)]
+    struct FlushRecorder {
+        pending: Vec<u8>,
+        flushes: Vec<Vec<u8>>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.pending.extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushes.push(std::mem::take(&mut self.pending));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flushes_only_on_newlines_and_drop() {
+        let mut recorder = FlushRecorder::default();
+        let mut output = LineBufferedOutput::new(&mut recorder);
+
+        for value in *b"ab\ncd" {
+            output.write(value);
+            assert!(output.inner().pending.is_empty());
+        }
+
+        assert_eq!(output.inner().flushes, [b"ab\n"]);
+        drop(output);
+        assert_eq!(recorder.flushes, [&b"ab\n"[..], b"cd"]);
+    }
+}
@@ -1,12 +1,17 @@
 //! Provides a trait that can be implemented to take output from a runner.
 
+pub mod line_buffered;
 pub mod map;
+pub mod printable;
+pub mod sink;
+pub mod tee;
+pub mod timed;
 
 use std::io::{Stdout, Write};
 use std::marker::PhantomData;
 use std::num::Wrapping;
 
-use self::map::Map;
+use self::{map::Map, printable::PrintableOutput, tee::Tee, timed::TimedOutput};
 
 /// Something which can provide input to a runner.
 pub trait RunnerOutput<T> {
@@ -24,6 +29,44 @@ pub trait RunnerOutput<T> {
             _phantom: PhantomData,
         }
     }
+
+    /// Passes each value given to `.write()` to both this output and `other`, which is useful for
+    /// capturing output while also showing it.
+    fn tee<B: RunnerOutput<T>>(self, other: B) -> Tee<Self, B>
+    where
+        Self: Sized,
+    {
+        Tee {
+            first: self,
+            second: other,
+        }
+    }
+
+    /// Replaces values outside of the printable ASCII range with `replacement`, so that
+    /// untrusted output can be safely shown in a terminal.
+    fn printable(self, replacement: T) -> PrintableOutput<Self, T>
+    where
+        Self: Sized,
+    {
+        PrintableOutput {
+            base: self,
+            replacement,
+        }
+    }
+
+    /// Records how many values are passed to `.write()` and when the first and last writes
+    /// happened.
+    fn timed(self) -> TimedOutput<Self>
+    where
+        Self: Sized,
+    {
+        TimedOutput {
+            base: self,
+            count: 0,
+            first: None,
+            last: None,
+        }
+    }
 }
 
 impl<T> RunnerOutput<T> for Vec<T> {
@@ -0,0 +1,47 @@
+//! Provides a struct implementing `RunnerOutput` that replaces non-printable values.
+
+use super::{DebuggableRunnerOutput, RunnerOutput};
+use crate::builder::types::CellValue;
+
+/// A struct implementing `RunnerOutput` that replaces values outside of the printable ASCII range
+/// (0x20 to 0x7E inclusive) with a replacement value before passing them to `.write()`.
+#[derive(Debug)]
+pub struct PrintableOutput<B, T> {
+    pub(super) base: B,
+    pub(super) replacement: T,
+}
+
+impl<T: CellValue, B: RunnerOutput<T>> RunnerOutput<T> for PrintableOutput<B, T> {
+    fn write(&mut self, value: T) {
+        if value
+            .try_into_isize()
+            .is_some_and(|value| (0x20..=0x7E).contains(&value))
+        {
+            self.base.write(value)
+        } else {
+            self.base.write(self.replacement)
+        }
+    }
+}
+
+impl<T, B: DebuggableRunnerOutput<T>> DebuggableRunnerOutput<T> for PrintableOutput<B, T> {
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.base.debug(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_control_bytes_and_keeps_printable_bytes() {
+        let mut output = Vec::<u8>::new().printable(b'?');
+
+        for value in *b"a\x07 ~\x7F\n\xFF" {
+            output.write(value);
+        }
+
+        assert_eq!(output.base, b"a? ~???");
+    }
+}
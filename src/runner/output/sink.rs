@@ -0,0 +1,37 @@
+//! Provides a struct implementing `RunnerOutput` that discards everything written to it.
+
+use super::{DebuggableRunnerOutput, RunnerOutput};
+
+/// A struct implementing `RunnerOutput` that ignores every value passed to `.write()`. This is
+/// useful when only the final memory of a runner matters, since nothing is stored.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sink;
+
+impl<T> RunnerOutput<T> for Sink {
+    fn write(&mut self, _value: T) {}
+}
+
+impl<T> DebuggableRunnerOutput<T> for Sink {
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("(sink)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+    use std::num::Wrapping;
+
+    #[test]
+    fn sink_discards_writes_without_affecting_memory() {
+        assert_eq!(std::mem::size_of::<Sink>(), 0);
+
+        let program = Program::from("+++++[>.+++.<-.]>>.-");
+        let runner = program.run::<4, _, _, Wrapping<u8>>(std::iter::empty(), Sink);
+        assert_eq!(
+            runner.memory(),
+            &[Wrapping(0), Wrapping(15), Wrapping(255), Wrapping(0)]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Provides a struct implementing `RunnerOutput` that passes every value to two outputs.
+
+use super::{DebuggableRunnerOutput, RunnerOutput};
+
+/// A struct implementing `RunnerOutput` that passes each value given to `.write()` to two outputs,
+/// first to `A` and then to `B`.
+#[derive(Debug)]
+pub struct Tee<A, B> {
+    pub(super) first: A,
+    pub(super) second: B,
+}
+
+impl<A, B> Tee<A, B> {
+    /// Gets the first output this struct is writing to.
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+
+    /// Gets the second output this struct is writing to.
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+
+    /// Gets both outputs this struct is writing to.
+    pub fn into_parts(self) -> (A, B) {
+        (self.first, self.second)
+    }
+}
+
+impl<T: Copy, A: RunnerOutput<T>, B: RunnerOutput<T>> RunnerOutput<T> for Tee<A, B> {
+    fn write(&mut self, value: T) {
+        self.first.write(value);
+        self.second.write(value);
+    }
+}
+
+impl<T, A: DebuggableRunnerOutput<T>, B> DebuggableRunnerOutput<T> for Tee<A, B> {
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.first.debug(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::Program;
+
+    #[test]
+    fn both_outputs_receive_every_write() {
+        let output = Vec::new().tee(Vec::new());
+        let runner = Program::from("+.++.+++.").run::<4, _, _, u8>(std::iter::empty(), output);
+        let (first, second) = runner.into_output().into_parts();
+
+        assert_eq!(first, [1, 3, 6]);
+        assert_eq!(second, first);
+    }
+}
@@ -0,0 +1,69 @@
+//! Provides a struct implementing `RunnerOutput` that measures how quickly values are written.
+
+use super::{DebuggableRunnerOutput, RunnerOutput};
+use std::time::{Duration, Instant};
+
+/// A struct implementing `RunnerOutput` that records the number of values passed to `.write()`, as
+/// well as the time of the first and last writes.
+#[derive(Debug)]
+pub struct TimedOutput<B> {
+    pub(super) base: B,
+    pub(super) count: usize,
+    pub(super) first: Option<Instant>,
+    pub(super) last: Option<Instant>,
+}
+
+impl<B> TimedOutput<B> {
+    /// Gets the number of values written so far and the time elapsed between the first and last
+    /// writes. The duration is zero if fewer than two values have been written.
+    pub fn stats(&self) -> (usize, Duration) {
+        let duration = match (self.first, self.last) {
+            (Some(first), Some(last)) => last - first,
+            _ => Duration::ZERO,
+        };
+
+        (self.count, duration)
+    }
+
+    /// Gets the output this struct is wrapping.
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+}
+
+impl<T, B: RunnerOutput<T>> RunnerOutput<T> for TimedOutput<B> {
+    fn write(&mut self, value: T) {
+        self.base.write(value);
+
+        let now = Instant::now();
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+        self.count += 1;
+    }
+}
+
+impl<T, B: DebuggableRunnerOutput<T>> DebuggableRunnerOutput<T> for TimedOutput<B> {
+    fn debug(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.base.debug(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_writes_and_measures_time_between_them() {
+        let mut output = Vec::<u8>::new().timed();
+        assert_eq!(output.stats(), (0, Duration::ZERO));
+
+        for value in [1, 2, 3, 4] {
+            output.write(value);
+        }
+
+        let (count, duration) = output.stats();
+        assert_eq!(count, 4);
+        assert!(duration >= Duration::ZERO);
+        assert_eq!(output.base(), &vec![1, 2, 3, 4]);
+    }
+}